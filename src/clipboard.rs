@@ -1,38 +1,367 @@
+use crate::warnings::Warnings;
 use anyhow::{Context, Result};
-use std::io::Write;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::io::{IsTerminal, Read, Write, stdout};
 use std::process::{Command, Stdio};
 
-/// Copy the provided text to the clipboard, trying common platform utilities.
-pub fn copy_to_clipboard(contents: &str) -> Result<()> {
-    let commands = [
-        ("pbcopy", &[][..]),
-        ("wl-copy", &[][..]),
-        ("xclip", &["-selection", "clipboard"]),
-        ("clip", &[][..]),
-    ];
-
-    for (cmd, args) in commands {
-        if try_copy(cmd, args, contents).unwrap_or(false) {
-            return Ok(());
+/// A way of putting text on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// A clipboard utility on PATH that reads the text from stdin.
+    Command {
+        name: &'static str,
+        args: &'static [&'static str],
+    },
+    /// The Win32 clipboard API, set directly as Unicode text. Avoids `clip`, which can
+    /// truncate large input and mangle UTF-8. Only available on Windows.
+    Windows,
+    /// The OSC 52 terminal escape sequence, which terminals forward to the local clipboard
+    /// (works over SSH). Only used when stdout is a terminal.
+    Osc52,
+}
+
+impl Backend {
+    fn name(self) -> &'static str {
+        match self {
+            Backend::Command { name, .. } => name,
+            Backend::Windows => "windows",
+            Backend::Osc52 => "osc52",
+        }
+    }
+
+    /// Why this backend was skipped when trying it returned `false`.
+    fn unavailable_reason(self) -> &'static str {
+        match self {
+            Backend::Command { .. } => "not found on PATH",
+            Backend::Windows => "only available on Windows",
+            Backend::Osc52 => "needs stdout to be a terminal",
+        }
+    }
+}
+
+/// Supported backends, in default detection order.
+const BACKENDS: &[Backend] = &[
+    Backend::Command {
+        name: "pbcopy",
+        args: &[],
+    },
+    Backend::Command {
+        name: "wl-copy",
+        args: &[],
+    },
+    Backend::Command {
+        name: "xclip",
+        args: &["-selection", "clipboard"],
+    },
+    Backend::Windows,
+    Backend::Command {
+        name: "clip",
+        args: &[],
+    },
+    Backend::Osc52,
+];
+
+/// Encoded OSC 52 payloads above this size are truncated or dropped by some terminals.
+const OSC52_SAFE_BYTES: usize = 100_000;
+
+/// Warn or fail before copying a payload that could freeze a clipboard manager.
+///
+/// Payloads over `max` bytes are an error; otherwise those over `warn_at` bytes get a warning.
+pub fn check_payload_size(
+    bytes: usize,
+    warn_at: u64,
+    max: Option<u64>,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    let bytes = bytes as u64;
+    if let Some(max) = max
+        && bytes > max
+    {
+        anyhow::bail!(
+            "bundle is {bytes} bytes, over --max-clipboard-size ({max} bytes); write it to a file with --output instead"
+        );
+    }
+    if bytes > warn_at {
+        warnings.push(format!(
+            "clipboard payload is {bytes} bytes; some clipboard managers freeze on payloads over {warn_at} bytes, so consider --output FILE"
+        ));
+    }
+    Ok(())
+}
+
+/// Names of the supported clipboard backends.
+pub fn backend_names() -> impl Iterator<Item = &'static str> {
+    BACKENDS.iter().map(|backend| backend.name())
+}
+
+/// Copy the provided text to the clipboard, trying common platform utilities and then
+/// OSC 52 when stdout is a terminal.
+///
+/// When `order` is given, only those backends are tried, in that order. If none works, the
+/// error lists why each one failed.
+pub fn copy_to_clipboard(
+    contents: &str,
+    order: Option<&[String]>,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    copy_with_first_working(contents, &ordered_backends(order), warnings)
+}
+
+fn copy_with_first_working(
+    contents: &str,
+    backends: &[Backend],
+    warnings: &mut Warnings,
+) -> Result<()> {
+    if backends.is_empty() {
+        anyhow::bail!("failed to copy to clipboard (no supported clipboard command found)");
+    }
+
+    let mut failures = Vec::with_capacity(backends.len());
+    for &backend in backends {
+        let copied = match backend {
+            Backend::Command { name, args } => try_copy(name, args, contents),
+            Backend::Windows => try_windows(contents),
+            Backend::Osc52 => try_osc52(contents, warnings),
+        };
+        match copied {
+            Ok(true) => return Ok(()),
+            Ok(false) => failures.push(format!(
+                "  {}: {}",
+                backend.name(),
+                backend.unavailable_reason()
+            )),
+            Err(err) => failures.push(format!("  {}: {err:#}", backend.name())),
         }
     }
 
-    anyhow::bail!("failed to copy to clipboard (no supported clipboard command found)");
+    anyhow::bail!(
+        "failed to copy to clipboard; every backend failed:\n{}",
+        failures.join("\n")
+    );
+}
+
+/// Copy with exactly one backend, failing with a specific reason instead of falling back.
+pub fn copy_with_backend(contents: &str, name: &str, warnings: &mut Warnings) -> Result<()> {
+    let backend = BACKENDS
+        .iter()
+        .find(|backend| backend.name() == name)
+        .copied()
+        .ok_or_else(|| {
+            let supported: Vec<&str> = backend_names().collect();
+            anyhow::anyhow!(
+                "unknown clipboard backend '{name}' (supported: {})",
+                supported.join(", ")
+            )
+        })?;
+
+    let copied = match backend {
+        Backend::Command { name, args } => try_copy(name, args, contents),
+        Backend::Windows => try_windows(contents),
+        Backend::Osc52 => try_osc52(contents, warnings),
+    }
+    .with_context(|| format!("clipboard backend '{}' failed", backend.name()))?;
+    if !copied {
+        anyhow::bail!(
+            "clipboard backend '{}' {}",
+            backend.name(),
+            backend.unavailable_reason()
+        );
+    }
+    Ok(())
 }
 
+fn ordered_backends(order: Option<&[String]>) -> Vec<Backend> {
+    match order {
+        Some(order) => order
+            .iter()
+            .filter_map(|name| {
+                BACKENDS
+                    .iter()
+                    .find(|backend| backend.name() == name)
+                    .copied()
+            })
+            .collect(),
+        None => BACKENDS.to_vec(),
+    }
+}
+
+/// Pipe `contents` into a clipboard command; returns `false` when it isn't on PATH.
+///
+/// When the command fails, the first line it wrote to stderr (such as xclip's "Error: Can't
+/// open display") becomes the error message.
 fn try_copy(cmd: &str, args: &[&str], contents: &str) -> Result<bool> {
-    let mut child = match Command::new(cmd).args(args).stdin(Stdio::piped()).spawn() {
+    let spawned = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
         Ok(child) => child,
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
         Err(e) => return Err(e).with_context(|| format!("failed to run '{cmd}'")),
     };
 
-    if let Some(stdin) = child.stdin.as_mut() {
-        stdin
-            .write_all(contents.as_bytes())
-            .with_context(|| format!("failed to write to '{cmd}' stdin"))?;
-    }
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(contents.as_bytes()),
+        None => Ok(()),
+    };
 
+    // A command that exits early closes its stdin, so its exit status says more than the
+    // broken pipe does.
+    // Tools like xclip and wl-copy stay in the background holding the clipboard, and would
+    // keep stderr open; it's only read once the command has failed.
     let status = child.wait()?;
-    Ok(status.success())
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => anyhow::bail!("{line} ({status})"),
+            None => anyhow::bail!("exited with {status}"),
+        }
+    }
+    written.with_context(|| format!("failed to write to '{cmd}' stdin"))?;
+    Ok(true)
+}
+
+/// Set `contents` as Unicode text through the Win32 clipboard API.
+#[cfg(windows)]
+fn try_windows(contents: &str) -> Result<bool> {
+    clipboard_win::set_clipboard_string(contents)
+        .map_err(|err| anyhow::anyhow!("failed to set the Windows clipboard: {err}"))?;
+    Ok(true)
+}
+
+/// The Win32 clipboard doesn't exist here; returns `false` so detection moves on.
+#[cfg(not(windows))]
+fn try_windows(_contents: &str) -> Result<bool> {
+    Ok(false)
+}
+
+/// Emit an OSC 52 sequence on stdout; returns `false` when stdout isn't a terminal.
+fn try_osc52(contents: &str, warnings: &mut Warnings) -> Result<bool> {
+    let mut out = stdout().lock();
+    if !out.is_terminal() {
+        return Ok(false);
+    }
+
+    let sequence = osc52_sequence(contents);
+    if sequence.len() > OSC52_SAFE_BYTES {
+        warnings.push(format!(
+            "OSC 52 payload is {} bytes; terminals often truncate or drop sequences over {OSC52_SAFE_BYTES} bytes",
+            sequence.len()
+        ));
+    }
+
+    out.write_all(sequence.as_bytes())
+        .and_then(|()| out.flush())
+        .context("failed to write OSC 52 sequence to the terminal")?;
+    Ok(true)
+}
+
+fn osc52_sequence(contents: &str) -> String {
+    format!("\x1b]52;c;{}\x07", BASE64.encode(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_payload_size_warns_then_fails() {
+        let mut warnings = Warnings::default();
+        check_payload_size(500, 1000, None, &mut warnings).unwrap();
+        assert!(warnings.messages().is_empty());
+
+        check_payload_size(1500, 1000, None, &mut warnings).unwrap();
+        assert_eq!(warnings.messages().len(), 1);
+        assert!(warnings.messages()[0].contains("--output"));
+
+        let err = check_payload_size(1500, 1000, Some(1200), &mut warnings).unwrap_err();
+        assert!(err.to_string().contains("--max-clipboard-size"));
+    }
+
+    #[test]
+    fn ordered_backends_follow_configured_order() {
+        let order = vec!["xclip".to_string(), "osc52".to_string()];
+        let names: Vec<&str> = ordered_backends(Some(&order))
+            .into_iter()
+            .map(Backend::name)
+            .collect();
+        assert_eq!(names, vec!["xclip", "osc52"]);
+        assert_eq!(ordered_backends(None).len(), BACKENDS.len());
+        assert_eq!(ordered_backends(None).last(), Some(&Backend::Osc52));
+    }
+
+    #[test]
+    fn forced_backend_reports_why_it_failed() {
+        let mut warnings = Warnings::default();
+        let err = copy_with_backend("x", "pbpaste", &mut warnings).unwrap_err();
+        assert!(err.to_string().contains("supported: pbcopy, wl-copy"));
+
+        assert!(!try_copy("gf-test-no-such-clipboard", &[], "x").unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn auto_detection_reports_each_backend_failure() {
+        let backends = [
+            Backend::Command {
+                name: "gf-test-no-such-clipboard",
+                args: &[],
+            },
+            Backend::Command {
+                name: "false",
+                args: &[],
+            },
+        ];
+        let err = copy_with_first_working("x", &backends, &mut Warnings::default()).unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("  gf-test-no-such-clipboard: not found on PATH\n"),
+            "{message}"
+        );
+        assert!(message.contains("  false: exited with"), "{message}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_command_reports_first_stderr_line() {
+        let err = try_copy(
+            "sh",
+            &[
+                "-c",
+                "echo >&2; echo \"Error: Can't open display: (null)\" >&2; exit 1",
+            ],
+            "x",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Can't open display: (null) (exit status: 1)"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_backend_round_trips_multibyte_text() {
+        let text = "héllo → 世界 🦀\r\nline two";
+        assert!(try_windows(text).unwrap());
+        assert_eq!(clipboard_win::get_clipboard_string().unwrap(), text);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn windows_backend_is_skipped_elsewhere() {
+        assert!(!try_windows("x").unwrap());
+        let err = copy_with_backend("x", "windows", &mut Warnings::default()).unwrap_err();
+        assert!(err.to_string().contains("only available on Windows"));
+    }
+
+    #[test]
+    fn osc52_sequence_wraps_base64_payload() {
+        assert_eq!(osc52_sequence("hi"), "\x1b]52;c;aGk=\x07");
+    }
 }
@@ -0,0 +1,91 @@
+use clap::ValueEnum;
+use std::io::IsTerminal;
+
+/// When to emit ANSI colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal and `NO_COLOR` is unset.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !no_color_requested(),
+        }
+    }
+}
+
+/// Honor https://no-color.org: any non-empty `NO_COLOR` disables automatic color.
+fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Applies colors to text destined for a particular stream.
+#[derive(Debug, Clone, Copy)]
+pub struct Painter {
+    enabled: bool,
+}
+
+impl Painter {
+    pub fn stdout(choice: ColorChoice) -> Self {
+        Self {
+            enabled: choice.enabled(std::io::stdout().is_terminal()),
+        }
+    }
+
+    pub fn stderr(choice: ColorChoice) -> Self {
+        Self {
+            enabled: choice.enabled(std::io::stderr().is_terminal()),
+        }
+    }
+
+    pub fn red(self, text: &str) -> String {
+        self.paint(text, "31")
+    }
+
+    pub fn green(self, text: &str) -> String {
+        self.paint(text, "32")
+    }
+
+    pub fn yellow(self, text: &str) -> String {
+        self.paint(text, "33")
+    }
+
+    pub fn dim(self, text: &str) -> String {
+        self.paint(text, "2")
+    }
+
+    fn paint(self, text: &str, code: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_choices_ignore_terminal_detection() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn painter_wraps_text_only_when_enabled() {
+        let on = Painter { enabled: true };
+        let off = Painter { enabled: false };
+        assert_eq!(on.red("error"), "\x1b[31merror\x1b[0m");
+        assert_eq!(off.red("error"), "error");
+    }
+}
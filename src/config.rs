@@ -1,21 +1,64 @@
+use crate::clipboard;
+use crate::gather::{IGNORED_DIRS, OutputFormat, SectionTemplate};
 use anyhow::{Context, Result};
+use globset::Glob;
 use indexmap::IndexMap;
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
 use std::fs;
+use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
 
-/// Parsed representation of `.gather-files.yaml`.
+/// Parsed representation of `.gather-files.yaml`, in the current (version 2) schema.
 #[derive(Debug, Deserialize)]
 pub struct ConfigFile {
     pub version: u32,
+    /// Schema URL or identifier for external validators; ignored by gf itself.
+    #[serde(default)]
+    pub schema: Option<String>,
     #[serde(default)]
     pub presets: IndexMap<String, Preset>,
+    /// Preset gathered by a bare `gf` instead of the whole repo.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Patterns identifying test files; replaces the built-in heuristics when set.
+    #[serde(default)]
+    pub test_patterns: Option<Vec<String>>,
+    /// Clipboard backends to try, in order; restricts auto-detection when set.
+    #[serde(default)]
+    pub clipboard_order: Option<Vec<String>>,
+    /// Exclude globs added to every preset's `exclude` and applied to path walks.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Extra directory names to skip while walking, added to the built-in list.
+    #[serde(default)]
+    pub ignore_dirs: Vec<String>,
+    /// Use `ignore_dirs` instead of the built-in list rather than adding to it.
+    #[serde(default)]
+    pub ignore_dirs_replace: bool,
+    /// Shell commands that transform file contents, keyed by extension (e.g. `ipynb`).
+    #[serde(default)]
+    pub content_filters: IndexMap<String, String>,
+    /// Move a README to the front of gathered files (default true).
+    #[serde(default)]
+    pub promote_readme: Option<bool>,
+    /// Extensions whose comments `--strip-comments` leaves alone (e.g. `sh`).
+    #[serde(default)]
+    pub keep_comments: Vec<String>,
+    /// Separator and `{path}` header for text-format sections.
+    #[serde(default)]
+    pub template: Option<SectionTemplate>,
 }
 
 /// A named preset describing which files to gather.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Preset {
+    /// Another preset whose include, exclude, and base are merged in before this one's.
+    #[serde(default)]
+    pub extends: Option<String>,
     /// Glob patterns to include (relative to `base` if provided).
+    #[serde(default)]
     pub include: Vec<String>,
     /// Glob patterns to exclude.
     #[serde(default)]
@@ -23,39 +66,967 @@ pub struct Preset {
     /// Optional base directory to apply includes/excludes against.
     #[serde(default)]
     pub base: Option<PathBuf>,
+    /// File listing paths (relative to `base`, one per line) to gather in order before `include`.
+    #[serde(default)]
+    pub from_file: Option<PathBuf>,
+    /// Other presets whose files this one gathers, in order; replaces the fields above.
+    #[serde(default)]
+    pub presets: Vec<String>,
+    /// Default output format when this preset is gathered (overridden by `--format`).
+    #[serde(default)]
+    pub format: Option<OutputFormat>,
+    /// Disabled presets are kept in the config but cannot be gathered.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Location of the per-user config: `$XDG_CONFIG_HOME/gather-files/config.yaml`, falling
+/// back to `~/.config/gather-files/config.yaml`.
+pub fn global_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("gather-files").join("config.yaml"))
+}
+
+fn read_error(path: &Path, err: io::Error) -> anyhow::Error {
+    match err.kind() {
+        ErrorKind::PermissionDenied => {
+            anyhow::anyhow!("config not readable: permission denied: {}", path.display())
+        }
+        ErrorKind::InvalidData => {
+            anyhow::anyhow!("config is not valid UTF-8: {}", path.display())
+        }
+        _ => anyhow::Error::new(err).context(format!("failed to read config: {}", path.display())),
+    }
+}
+
+/// Newest config version; `gf init` writes it and older versions still load.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Top-level fields introduced in version 2, rejected in a `version: 1` file.
+const V2_FIELDS: &[&str] = &[
+    "default",
+    "exclude",
+    "keep_comments",
+    "promote_readme",
+    "template",
+];
+
+/// Preset fields introduced in version 2, rejected in a `version: 1` file.
+const V2_PRESET_FIELDS: &[&str] = &["from_file", "presets"];
+
+/// Check that `value` only uses fields its declared version supports.
+///
+/// Unknown versions are left to validation, which reports them with every other problem.
+fn check_version_fields(value: &serde_yaml::Value) -> Result<()> {
+    if value.get("version").and_then(serde_yaml::Value::as_u64) != Some(1) {
+        return Ok(());
+    }
+
+    let mut newer: Vec<String> = V2_FIELDS
+        .iter()
+        .filter(|field| value.get(**field).is_some())
+        .map(|field| format!("`{field}`"))
+        .collect();
+    if let Some(presets) = value.get("presets").and_then(serde_yaml::Value::as_mapping) {
+        for (name, preset) in presets {
+            let name = name.as_str().unwrap_or_default();
+            newer.extend(
+                V2_PRESET_FIELDS
+                    .iter()
+                    .filter(|field| preset.get(**field).is_some())
+                    .map(|field| format!("`presets.{name}.{field}`")),
+            );
+        }
+    }
+    if newer.is_empty() {
+        return Ok(());
+    }
+    let verb = if newer.len() == 1 {
+        "requires"
+    } else {
+        "require"
+    };
+    anyhow::bail!(
+        "{} {verb} `version: 2`; change `version: 1` to `version: 2` (version 2 reads every version 1 config unchanged)",
+        newer.join(", ")
+    )
+}
+
+/// Read a TOML config: the `[tool.gather-files]` table when there is one (as in
+/// `pyproject.toml`), otherwise the whole document laid out like the YAML config
+/// (`version = 2`, `[presets.src]`, ...).
+fn toml_config_value(contents: &str) -> Result<serde_yaml::Value> {
+    let mut value: serde_yaml::Value = toml::from_str(contents)?;
+    if value.get("tool").is_some() {
+        return value
+            .get_mut("tool")
+            .and_then(|tool| tool.get_mut("gather-files"))
+            .map(std::mem::take)
+            .ok_or_else(|| anyhow::anyhow!("no [tool.gather-files] table"));
+    }
+    Ok(value)
+}
+
+/// Expand `$VAR` and `${VAR}` anywhere in `value`, and `~` when it starts the value.
+///
+/// A `$` not followed by a variable name is kept as is. Unset variables are an error.
+fn expand_vars(value: &str, lookup: &impl Fn(&str) -> Option<String>) -> Result<String> {
+    let var = |name: &str| {
+        lookup(name).ok_or_else(|| anyhow::anyhow!("environment variable `{name}` is not set"))
+    };
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") {
+        output.push_str(&var("HOME")?);
+        rest = &rest[1..];
+    }
+    while let Some(dollar) = rest.find('$') {
+        output.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("unterminated `${{` in '{value}'"))?;
+            output.push_str(&var(&braced[..end])?);
+            rest = &braced[end + 1..];
+        } else {
+            let end = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+            if end == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+                output.push('$');
+                rest = after;
+            } else {
+                output.push_str(&var(&after[..end])?);
+                rest = &after[end..];
+            }
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
 }
 
 impl ConfigFile {
     /// Load configuration from disk if the file exists.
+    ///
+    /// A missing file is `Ok(None)`; a file that exists but can't be read or parsed is an error.
+    /// This is [`ConfigFile::load_merged`] without a global config.
     pub fn load(path: &Path) -> Result<Option<Self>> {
-        if !path.exists() {
-            return Ok(None);
+        Self::load_merged(None, path)
+    }
+
+    /// Load the repo config layered over an optional global config.
+    ///
+    /// Global presets come first; a repo preset with the same name replaces the global one
+    /// in place. Either file may be missing; `Ok(None)` means neither exists.
+    pub fn load_merged(global: Option<&Path>, path: &Path) -> Result<Option<Self>> {
+        let global = match global {
+            Some(global_path) => {
+                let config = Self::parse(global_path)?;
+                // The merged config takes the repo's version, so check the global one here.
+                if let Some(config) = &config
+                    && !(1..=CURRENT_VERSION).contains(&config.version)
+                {
+                    anyhow::bail!(
+                        "unsupported config version {} (expected 1 or 2) in global config {}",
+                        config.version,
+                        global_path.display()
+                    );
+                }
+                config
+            }
+            None => None,
+        };
+
+        let mut config = match (global, Self::parse(path)?) {
+            (None, None) => return Ok(None),
+            (Some(config), None) | (None, Some(config)) => config,
+            (Some(global), Some(repo)) => global.merge(repo),
+        };
+        config.validate()?;
+        config.resolve_extends();
+        Ok(Some(config))
+    }
+
+    /// Read and parse one config file without validating it.
+    fn parse(path: &Path) -> Result<Option<Self>> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(read_error(path, err)),
+        };
+        if metadata.is_dir() {
+            anyhow::bail!("config path is a directory: {}", path.display());
         }
 
-        let contents = fs::read_to_string(path)
-            .with_context(|| format!("failed to read config: {}", path.display()))?;
-        let config: ConfigFile = serde_yaml::from_str(&contents)
+        let contents = fs::read_to_string(path).map_err(|err| read_error(path, err))?;
+        let value = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml_config_value(&contents)
+        } else {
+            // Typed maps keep the last duplicate key silently; `Value` rejects duplicates.
+            serde_yaml::from_str::<serde_yaml::Value>(&contents).map_err(Into::into)
+        }
+        .with_context(|| format!("failed to parse config: {}", path.display()))?;
+        check_version_fields(&value)
             .with_context(|| format!("failed to parse config: {}", path.display()))?;
-        config.validate()?;
+        let mut config: Self = serde_yaml::from_value(value)
+            .with_context(|| format!("failed to parse config: {}", path.display()))?;
+        config
+            .expand_env_vars(|name| std::env::var(name).ok())
+            .with_context(|| format!("failed to expand config: {}", path.display()))?;
         Ok(Some(config))
     }
 
+    /// Expand `$VAR`, `${VAR}`, and a leading `~` in each preset's paths and patterns.
+    fn expand_env_vars(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+        for (name, preset) in &mut self.presets {
+            let context = || format!("preset '{name}'");
+            if let Some(base) = &preset.base {
+                let expanded =
+                    expand_vars(&base.to_string_lossy(), &lookup).with_context(context)?;
+                preset.base = Some(PathBuf::from(expanded));
+            }
+            if let Some(from_file) = &preset.from_file {
+                let expanded =
+                    expand_vars(&from_file.to_string_lossy(), &lookup).with_context(context)?;
+                preset.from_file = Some(PathBuf::from(expanded));
+            }
+            for pattern in preset.include.iter_mut().chain(preset.exclude.iter_mut()) {
+                *pattern = expand_vars(pattern, &lookup).with_context(context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Layer `repo` over `self` (the global config): repo presets and settings win.
+    fn merge(self, repo: Self) -> Self {
+        let mut presets = self.presets;
+        presets.extend(repo.presets);
+        let mut content_filters = self.content_filters;
+        content_filters.extend(repo.content_filters);
+        let mut exclude = self.exclude;
+        exclude.extend(repo.exclude);
+        let mut ignore_dirs = self.ignore_dirs;
+        ignore_dirs.extend(repo.ignore_dirs);
+        let mut keep_comments = self.keep_comments;
+        keep_comments.extend(repo.keep_comments);
+
+        ConfigFile {
+            version: repo.version,
+            schema: repo.schema,
+            presets,
+            default: repo.default.or(self.default),
+            test_patterns: repo.test_patterns.or(self.test_patterns),
+            clipboard_order: repo.clipboard_order.or(self.clipboard_order),
+            exclude,
+            ignore_dirs,
+            ignore_dirs_replace: self.ignore_dirs_replace || repo.ignore_dirs_replace,
+            content_filters,
+            promote_readme: repo.promote_readme.or(self.promote_readme),
+            keep_comments,
+            template: repo.template.or(self.template),
+        }
+    }
+
     fn validate(&self) -> Result<()> {
-        if self.version != 1 {
-            anyhow::bail!("unsupported config version {} (expected 1)", self.version);
+        let errors = self.validation_errors();
+        match errors.as_slice() {
+            [] => Ok(()),
+            [error] => anyhow::bail!("{error}"),
+            _ => anyhow::bail!(
+                "{} problems found:\n  - {}",
+                errors.len(),
+                errors.join("\n  - ")
+            ),
+        }
+    }
+
+    /// Collect every validation problem instead of stopping at the first.
+    fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !(1..=CURRENT_VERSION).contains(&self.version) {
+            errors.push(format!(
+                "unsupported config version {} (expected 1 or 2)",
+                self.version
+            ));
         }
 
+        errors.extend(self.extends_errors());
+        errors.extend(self.group_errors());
+        if let Some(name) = &self.default {
+            match self.presets.get(name) {
+                None => errors.push(format!("default preset '{name}' is not defined")),
+                Some(preset) if !preset.enabled => {
+                    errors.push(format!("default preset '{name}' is disabled"))
+                }
+                Some(_) => {}
+            }
+        }
         for (name, preset) in &self.presets {
-            if preset.include.is_empty() {
-                anyhow::bail!("preset '{name}' must define at least one include pattern");
+            if preset.include.is_empty()
+                && preset.extends.is_none()
+                && preset.from_file.is_none()
+                && preset.presets.is_empty()
+            {
+                errors.push(format!(
+                    "preset '{name}' must define at least one include pattern or a from_file list"
+                ));
+            }
+            for pattern in preset.include.iter().chain(&preset.exclude) {
+                if let Err(err) = Glob::new(pattern) {
+                    errors.push(format!(
+                        "preset '{name}' has invalid glob '{pattern}': {err}"
+                    ));
+                }
             }
         }
 
-        Ok(())
+        for pattern in &self.exclude {
+            if let Err(err) = Glob::new(pattern) {
+                errors.push(format!("invalid exclude pattern '{pattern}': {err}"));
+            }
+        }
+
+        for pattern in self.test_patterns.iter().flatten() {
+            if let Err(err) = Glob::new(pattern) {
+                errors.push(format!("invalid test pattern '{pattern}': {err}"));
+            }
+        }
+
+        for name in self.clipboard_order.iter().flatten() {
+            if !clipboard::backend_names().any(|backend| backend == name) {
+                let supported: Vec<&str> = clipboard::backend_names().collect();
+                errors.push(format!(
+                    "unknown clipboard backend '{name}' (supported: {})",
+                    supported.join(", ")
+                ));
+            }
+        }
+
+        for (extension, command) in &self.content_filters {
+            if command.trim().is_empty() {
+                errors.push(format!(
+                    "content filter for '{extension}' has an empty command"
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Report `extends` references to missing presets and inheritance cycles (each once).
+    fn extends_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut reported_cycles = HashSet::new();
+        for (name, preset) in &self.presets {
+            let mut chain = vec![name.as_str()];
+            let mut current = preset;
+            while let Some(parent) = current.extends.as_deref() {
+                if let Some(start) = chain.iter().position(|link| *link == parent) {
+                    let mut cycle = chain[start..].to_vec();
+                    let mut key = cycle.clone();
+                    key.sort_unstable();
+                    if reported_cycles.insert(key) {
+                        cycle.push(parent);
+                        errors.push(format!("preset inheritance cycle: {}", cycle.join(" -> ")));
+                    }
+                    break;
+                }
+
+                match self.presets.get(parent) {
+                    Some(next) => {
+                        chain.push(parent);
+                        current = next;
+                    }
+                    None => {
+                        // Only the direct child reports it, so the error isn't repeated per descendant.
+                        if chain.len() == 1 {
+                            errors
+                                .push(format!("preset '{name}' extends unknown preset '{parent}'"));
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    /// Report group presets that set their own patterns, list unknown presets, or form
+    /// cycles (each once).
+    fn group_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (name, preset) in &self.presets {
+            if preset.presets.is_empty() {
+                continue;
+            }
+            let own_fields = [
+                ("extends", preset.extends.is_some()),
+                ("include", !preset.include.is_empty()),
+                ("exclude", !preset.exclude.is_empty()),
+                ("base", preset.base.is_some()),
+                ("from_file", preset.from_file.is_some()),
+            ];
+            let set: Vec<&str> = own_fields
+                .iter()
+                .filter(|(_, set)| *set)
+                .map(|(field, _)| *field)
+                .collect();
+            if !set.is_empty() {
+                errors.push(format!(
+                    "preset '{name}' lists presets, so it can't also set {}",
+                    set.join(", ")
+                ));
+            }
+            for member in &preset.presets {
+                if !self.presets.contains_key(member) {
+                    errors.push(format!("preset '{name}' lists unknown preset '{member}'"));
+                }
+            }
+        }
+
+        let mut reported_cycles = HashSet::new();
+        for name in self.presets.keys() {
+            let mut chain = vec![name.as_str()];
+            self.find_group_cycles(&mut chain, &mut reported_cycles, &mut errors);
+        }
+        errors
+    }
+
+    /// Depth-first search from the last preset in `chain` for a member already on it.
+    fn find_group_cycles<'a>(
+        &'a self,
+        chain: &mut Vec<&'a str>,
+        reported_cycles: &mut HashSet<Vec<&'a str>>,
+        errors: &mut Vec<String>,
+    ) {
+        let Some(preset) = chain.last().and_then(|name| self.presets.get(*name)) else {
+            return;
+        };
+        for member in &preset.presets {
+            if let Some(start) = chain.iter().position(|link| link == member) {
+                let mut cycle = chain[start..].to_vec();
+                let mut key = cycle.clone();
+                key.sort_unstable();
+                if reported_cycles.insert(key) {
+                    cycle.push(member);
+                    errors.push(format!("preset group cycle: {}", cycle.join(" -> ")));
+                }
+                continue;
+            }
+            chain.push(member);
+            self.find_group_cycles(chain, reported_cycles, errors);
+            chain.pop();
+        }
+    }
+
+    /// Replace each preset with its parents' patterns merged in. Requires a validated,
+    /// acyclic config.
+    fn resolve_extends(&mut self) {
+        let resolved: IndexMap<String, Preset> = self
+            .presets
+            .keys()
+            .map(|name| (name.clone(), self.resolved_preset(name)))
+            .collect();
+        self.presets = resolved;
+    }
+
+    fn resolved_preset(&self, name: &str) -> Preset {
+        let preset = &self.presets[name];
+        let Some(parent) = preset.extends.as_deref() else {
+            return preset.clone();
+        };
+
+        let parent = self.resolved_preset(parent);
+        Preset {
+            include: [parent.include, preset.include.clone()].concat(),
+            exclude: [parent.exclude, preset.exclude.clone()].concat(),
+            base: preset.base.clone().or(parent.base),
+            from_file: preset.from_file.clone().or(parent.from_file),
+            ..preset.clone()
+        }
+    }
+
+    /// JSON schema describing every supported config version, for editors and CI validators.
+    pub fn json_schema() -> serde_json::Value {
+        let patterns = json!({ "type": "array", "items": { "type": "string" } });
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "gather-files config",
+            "type": "object",
+            "required": ["version"],
+            "if": { "properties": { "version": { "const": 1 } } },
+            "then": {
+                "not": {
+                    "anyOf": V2_FIELDS.iter().map(|field| json!({ "required": [field] })).collect::<Vec<_>>()
+                },
+                "properties": {
+                    "presets": {
+                        "additionalProperties": {
+                            "not": {
+                                "anyOf": V2_PRESET_FIELDS.iter().map(|field| json!({ "required": [field] })).collect::<Vec<_>>()
+                            }
+                        }
+                    }
+                }
+            },
+            "additionalProperties": false,
+            "properties": {
+                "version": { "enum": [1, CURRENT_VERSION] },
+                "schema": { "type": "string" },
+                "default": { "type": "string" },
+                "presets": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "anyOf": [
+                            { "required": ["include"] },
+                            { "required": ["extends"] },
+                            { "required": ["from_file"] },
+                            { "required": ["presets"] }
+                        ],
+                        "additionalProperties": false,
+                        "properties": {
+                            "extends": { "type": "string" },
+                            "include": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+                            "exclude": patterns,
+                            "base": { "type": "string" },
+                            "from_file": { "type": "string" },
+                            "presets": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+                            "format": { "enum": ["text", "markdown", "xml", "json"] },
+                            "enabled": { "type": "boolean" }
+                        }
+                    }
+                },
+                "exclude": patterns,
+                "test_patterns": patterns,
+                "clipboard_order": {
+                    "type": "array",
+                    "items": { "enum": clipboard::backend_names().collect::<Vec<_>>() }
+                },
+                "ignore_dirs": patterns,
+                "ignore_dirs_replace": { "type": "boolean" },
+                "content_filters": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" }
+                },
+                "promote_readme": { "type": "boolean" },
+                "keep_comments": patterns,
+                "template": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "header": { "type": "string" },
+                        "separator": { "type": "string" }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Directory names to skip while walking: the built-in list plus `ignore_dirs`,
+    /// or `ignore_dirs` alone when `ignore_dirs_replace` is set.
+    pub fn ignored_dirs(&self) -> Vec<String> {
+        let mut dirs: Vec<String> = if self.ignore_dirs_replace {
+            Vec::new()
+        } else {
+            IGNORED_DIRS.iter().map(|dir| dir.to_string()).collect()
+        };
+        for dir in &self.ignore_dirs {
+            if !dirs.contains(dir) {
+                dirs.push(dir.clone());
+            }
+        }
+        dirs
     }
 
     /// Fetch a preset by name.
     pub fn preset(&self, name: &str) -> Option<&Preset> {
         self.presets.get(name)
     }
+
+    /// Iterate over presets that are not disabled, in config order.
+    pub fn enabled_presets(&self) -> impl Iterator<Item = (&String, &Preset)> {
+        self.presets.iter().filter(|(_, preset)| preset.enabled)
+    }
+
+    /// The enabled preset name closest to a mistyped `name`, if any is within a few edits.
+    pub fn closest_preset(&self, name: &str) -> Option<&str> {
+        let len = name.chars().count();
+        let max_distance = (len / 3).max(2);
+        self.enabled_presets()
+            .map(|(candidate, _)| (edit_distance(name, candidate), candidate.as_str()))
+            .filter(|&(distance, _)| distance <= max_distance && distance < len)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    /// The "preset not found" error, with a suggestion when a preset name is close.
+    pub fn missing_preset_error(&self, name: &str) -> anyhow::Error {
+        match self.closest_preset(name) {
+            Some(closest) => {
+                anyhow::anyhow!("preset '{name}' not found in config; did you mean '{closest}'?")
+            }
+            None => anyhow::anyhow!("preset '{name}' not found in config"),
+        }
+    }
+}
+
+/// Levenshtein distance between two strings, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn load_yaml(yaml: &str) -> Result<Option<ConfigFile>> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".gather-files.yaml");
+        fs::write(&path, yaml).unwrap();
+        ConfigFile::load(&path)
+    }
+
+    #[test]
+    fn closest_preset_suggests_only_near_enabled_names() {
+        assert_eq!(edit_distance("scr", "src"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+        let config = load_yaml(
+            "version: 1\npresets:\n  src:\n    include: [\"**/*.rs\"]\n  frontend:\n    include: [\"web/**\"]\n  old:\n    enabled: false\n    include: [\"old/**\"]\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.closest_preset("scr"), Some("src"));
+        assert_eq!(config.closest_preset("fronted"), Some("frontend"));
+        assert_eq!(config.closest_preset("docs"), None);
+        assert_eq!(config.closest_preset("ol"), None);
+    }
+
+    #[test]
+    fn expand_vars_substitutes_variables_and_home() {
+        let lookup = |name: &str| match name {
+            "PROJECT_ROOT" => Some("/work/mono".to_string()),
+            "HOME" => Some("/home/dev".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_vars("$PROJECT_ROOT/services/api", &lookup).unwrap(),
+            "/work/mono/services/api"
+        );
+        assert_eq!(
+            expand_vars("${PROJECT_ROOT}_old/**/*.rs", &lookup).unwrap(),
+            "/work/mono_old/**/*.rs"
+        );
+        assert_eq!(expand_vars("~/notes", &lookup).unwrap(), "/home/dev/notes");
+        assert_eq!(expand_vars("a~/$/b$1", &lookup).unwrap(), "a~/$/b$1");
+
+        let err = expand_vars("$MISSING/src", &lookup).unwrap_err();
+        assert!(err.to_string().contains("`MISSING`"), "{err}");
+        assert!(expand_vars("${PROJECT_ROOT", &lookup).is_err());
+    }
+
+    #[test]
+    fn expand_env_vars_names_the_preset() {
+        let mut config: ConfigFile = serde_yaml::from_str(
+            "version: 1\npresets:\n  api:\n    base: $GF_TEST_UNSET_ROOT/api\n    include: [\"**/*.rs\"]\n",
+        )
+        .unwrap();
+        let err = config.expand_env_vars(|_| None).unwrap_err();
+        assert!(format!("{err:#}").contains("preset 'api'"), "{err:#}");
+
+        config
+            .expand_env_vars(|_| Some("/srv".to_string()))
+            .unwrap();
+        assert_eq!(config.presets["api"].base, Some(PathBuf::from("/srv/api")));
+    }
+
+    #[test]
+    fn validate_checks_group_presets() {
+        let err = load_yaml(
+            "version: 2\npresets:\n  src:\n    include: [\"src/**\"]\n  a:\n    presets: [src, b]\n  b:\n    presets: [a, missing]\n    include: [\"*\"]\n",
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("preset 'b' lists presets, so it can't also set include"),
+            "{message}"
+        );
+        assert!(
+            message.contains("preset 'b' lists unknown preset 'missing'"),
+            "{message}"
+        );
+        assert_eq!(
+            message.matches("preset group cycle").count(),
+            1,
+            "{message}"
+        );
+        assert!(message.contains("a -> b -> a"), "{message}");
+
+        let config = load_yaml(
+            "version: 2\npresets:\n  src:\n    include: [\"src/**\"]\n  all:\n    presets: [src]\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.presets["all"].presets, ["src"]);
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let err = load_yaml(
+            "version: 3\npresets:\n  empty:\n    include: []\n  bad:\n    include: [\"src/[\"]\n",
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("3 problems found:"));
+        assert!(message.contains("unsupported config version 3"));
+        assert!(message.contains("preset 'empty' must define at least one include pattern"));
+        assert!(message.contains("preset 'bad' has invalid glob 'src/['"));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_clipboard_backends() {
+        let err = load_yaml("version: 1\nclipboard_order: [xclip, pbpaste]\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown clipboard backend 'pbpaste'")
+        );
+        assert!(load_yaml("version: 1\nclipboard_order: [wl-copy, xclip]\n").is_ok());
+    }
+
+    #[test]
+    fn validate_requires_default_preset_to_exist() {
+        let preset = "presets:\n  web:\n    include: [\"**/*.ts\"]\n";
+        let config = load_yaml(&format!("version: 2\ndefault: web\n{preset}"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.default.as_deref(), Some("web"));
+
+        let err = load_yaml(&format!("version: 2\ndefault: api\n{preset}")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("default preset 'api' is not defined")
+        );
+    }
+
+    #[test]
+    fn version_one_rejects_version_two_fields() {
+        let err =
+            load_yaml("version: 1\nexclude: [\"**/*.lock\"]\ntemplate:\n  header: \"{path}\"\n")
+                .unwrap_err();
+        assert!(format!("{err:#}").ends_with(
+            "`exclude`, `template` require `version: 2`; change `version: 1` to `version: 2` (version 2 reads every version 1 config unchanged)"
+        ));
+
+        let listed = "presets:\n  docs:\n    from_file: docs.txt\n";
+        let err = load_yaml(&format!("version: 1\n{listed}")).unwrap_err();
+        assert!(format!("{err:#}").contains("`presets.docs.from_file` requires `version: 2`"));
+
+        let composed = "presets:\n  src:\n    include: [\"src/**\"]\n  all:\n    presets: [src]\n";
+        let err = load_yaml(&format!("version: 1\n{composed}")).unwrap_err();
+        assert!(format!("{err:#}").contains("`presets.all.presets` requires `version: 2`"));
+        assert!(load_yaml(&format!("version: 2\n{composed}")).is_ok());
+
+        let config = load_yaml("version: 2\nexclude: [\"**/*.lock\"]\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.exclude, vec!["**/*.lock"]);
+        assert!(load_yaml("version: 1\nignore_dirs: [dist]\n").is_ok());
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let config = load_yaml(
+            "version: 1\npresets:\n  rust:\n    description: \"rust code\"\n    include: [\"**/*.rs\"]\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.presets["rust"].include, vec!["**/*.rs"]);
+    }
+
+    #[test]
+    fn load_distinguishes_missing_config_from_directory() {
+        let dir = tempdir().unwrap();
+        assert!(
+            ConfigFile::load(&dir.path().join("missing.yaml"))
+                .unwrap()
+                .is_none()
+        );
+
+        let err = ConfigFile::load(dir.path()).unwrap_err();
+        assert!(err.to_string().starts_with("config path is a directory"));
+    }
+
+    #[test]
+    fn schema_field_is_accepted_and_described() {
+        let config = load_yaml("version: 1\nschema: ./gather-files.schema.json\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.schema.as_deref(), Some("./gather-files.schema.json"));
+
+        let schema = ConfigFile::json_schema();
+        assert_eq!(schema["properties"]["version"]["enum"], json!([1, 2]));
+        assert_eq!(schema["then"]["not"]["anyOf"][0]["required"][0], "default");
+        assert_eq!(
+            schema["then"]["properties"]["presets"]["additionalProperties"]["not"]["anyOf"][0]["required"]
+                [0],
+            "from_file"
+        );
+        assert!(schema["properties"]["schema"].is_object());
+        assert!(
+            schema["properties"]["presets"]["additionalProperties"]["properties"]["include"]
+                .is_object()
+        );
+    }
+
+    #[test]
+    fn ignore_dirs_extend_or_replace_builtins() {
+        let extended = load_yaml("version: 1\nignore_dirs: [dist, target]\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            extended.ignored_dirs(),
+            vec![".git", "target", "node_modules", "dist"]
+        );
+
+        let replaced =
+            load_yaml("version: 1\nignore_dirs: [.git, vendor]\nignore_dirs_replace: true\n")
+                .unwrap()
+                .unwrap();
+        assert_eq!(replaced.ignored_dirs(), vec![".git", "vendor"]);
+    }
+
+    #[test]
+    fn extends_merges_parent_patterns_and_base() {
+        let config = load_yaml(
+            "version: 1\npresets:\n  common:\n    base: app\n    include: [\"README.md\"]\n    exclude: [\"**/*.min.js\"]\n  web:\n    extends: common\n    include: [\"**/*.ts\"]\n  web_lib:\n    extends: web\n    base: lib\n    exclude: [\"dist/**\"]\n",
+        )
+        .unwrap()
+        .unwrap();
+        let web_lib = config.preset("web_lib").unwrap();
+        assert_eq!(web_lib.include, vec!["README.md", "**/*.ts"]);
+        assert_eq!(web_lib.exclude, vec!["**/*.min.js", "dist/**"]);
+        assert_eq!(web_lib.base.as_deref(), Some(Path::new("lib")));
+        assert_eq!(
+            config.preset("web").unwrap().base.as_deref(),
+            Some(Path::new("app"))
+        );
+    }
+
+    #[test]
+    fn extends_reports_cycles_and_unknown_parents() {
+        let err = load_yaml(
+            "version: 1\npresets:\n  a:\n    extends: b\n  b:\n    extends: a\n  c:\n    extends: a\n  d:\n    extends: missing\n",
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("2 problems found:"));
+        assert!(message.contains("preset inheritance cycle: a -> b -> a"));
+        assert!(message.contains("preset 'd' extends unknown preset 'missing'"));
+    }
+
+    #[test]
+    fn load_merged_layers_repo_over_global() {
+        let dir = tempdir().unwrap();
+        let global = dir.path().join("global.yaml");
+        let repo = dir.path().join(".gather-files.yaml");
+        fs::write(
+            &global,
+            "version: 1\nclipboard_order: [xclip]\npresets:\n  docs:\n    include: [\"docs/**\"]\n  rust:\n    include: [\"**/*.rs\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            &repo,
+            "version: 1\npresets:\n  app:\n    extends: docs\n    include: [\"app/**\"]\n  rust:\n    include: [\"src/**/*.rs\"]\n",
+        )
+        .unwrap();
+
+        let config = ConfigFile::load_merged(Some(&global), &repo)
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = config.presets.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["docs", "rust", "app"]);
+        assert_eq!(config.preset("rust").unwrap().include, vec!["src/**/*.rs"]);
+        assert_eq!(
+            config.preset("app").unwrap().include,
+            vec!["docs/**", "app/**"]
+        );
+        assert_eq!(
+            config.clipboard_order.as_deref(),
+            Some(&["xclip".to_string()][..])
+        );
+
+        let missing = dir.path().join("missing.yaml");
+        assert!(
+            ConfigFile::load_merged(Some(&global), &missing)
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            ConfigFile::load_merged(Some(&missing), &missing)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn load_reads_toml_root_layout_and_tool_table() {
+        let dir = tempdir().unwrap();
+        let root_layout = dir.path().join("gf.toml");
+        fs::write(
+            &root_layout,
+            "version = 2\nexclude = [\"**/*.lock\"]\n\n[presets.src]\ninclude = [\"src/**\"]\nformat = \"markdown\"\n",
+        )
+        .unwrap();
+        let config = ConfigFile::load(&root_layout).unwrap().unwrap();
+        assert_eq!(config.exclude, vec!["**/*.lock"]);
+        let preset = config.preset("src").unwrap();
+        assert_eq!(preset.include, vec!["src/**"]);
+        assert_eq!(preset.format, Some(OutputFormat::Markdown));
+
+        let pyproject = dir.path().join("pyproject.toml");
+        fs::write(
+            &pyproject,
+            "[project]\nname = \"app\"\n\n[tool.black]\nline-length = 100\n\n[tool.gather-files]\nversion = 1\n\n[tool.gather-files.presets.app]\ninclude = [\"app/**/*.py\"]\n",
+        )
+        .unwrap();
+        let config = ConfigFile::load(&pyproject).unwrap().unwrap();
+        assert_eq!(config.preset("app").unwrap().include, vec!["app/**/*.py"]);
+
+        fs::write(&pyproject, "[tool.black]\nline-length = 100\n").unwrap();
+        let err = ConfigFile::load(&pyproject).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("no [tool.gather-files] table"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn load_rejects_duplicate_preset_names() {
+        let err = load_yaml(
+            "version: 1\npresets:\n  a:\n    include: [\"x\"]\n  a:\n    include: [\"y\"]\n",
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("duplicate entry with key \"a\""));
+    }
 }
@@ -0,0 +1,99 @@
+use crate::clipboard;
+use crate::warnings::Warnings;
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{Write, stdout};
+use std::path::{Path, PathBuf};
+
+/// Where the rendered bundle goes; only `Clipboard` spawns a clipboard tool.
+pub enum Destination<'a> {
+    /// Copy using the configured backend order, if any.
+    Clipboard(Option<&'a [String]>),
+    /// Copy with one backend chosen by `--clipboard`.
+    ClipboardBackend(&'a str),
+    Stdout,
+    /// Render but drop the result (`--no-clipboard`).
+    Discard,
+    File(PathBuf),
+    /// Add to the end of a file (`--output` with `--append`).
+    AppendFile(PathBuf),
+}
+
+impl Destination<'_> {
+    pub fn is_clipboard(&self) -> bool {
+        matches!(
+            self,
+            Destination::Clipboard(_) | Destination::ClipboardBackend(_)
+        )
+    }
+
+    /// What delivering does, as `--profile` labels it.
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Destination::Clipboard(_) | Destination::ClipboardBackend(_) => "copy",
+            Destination::Discard => "discard",
+            Destination::Stdout | Destination::File(_) | Destination::AppendFile(_) => "write",
+        }
+    }
+
+    /// Send `contents` here: copy, print, write, or drop it.
+    pub fn deliver(&self, contents: &str, warnings: &mut Warnings) -> Result<()> {
+        match self {
+            Destination::Clipboard(order) => {
+                clipboard::copy_to_clipboard(contents, *order, warnings)
+            }
+            Destination::ClipboardBackend(backend) => {
+                clipboard::copy_with_backend(contents, backend, warnings)
+            }
+            Destination::Stdout => {
+                let mut out = stdout().lock();
+                out.write_all(contents.as_bytes())
+                    .and_then(|()| out.flush())
+                    .context("failed to write output to stdout")
+            }
+            Destination::Discard => Ok(()),
+            Destination::File(path) => write_output_file(path, contents, false),
+            Destination::AppendFile(path) => write_output_file(path, contents, true),
+        }
+    }
+}
+
+/// Write the bundle to `path`, creating parent directories and either truncating existing
+/// contents or, with `append`, adding to the end.
+fn write_output_file(path: &Path, contents: &str, append: bool) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn write_output_file_creates_parents_and_truncates_or_appends() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("context/bundle.txt");
+
+        write_output_file(&path, "first run, longer contents\n", false).unwrap();
+        write_output_file(&path, "second\n", false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+
+        write_output_file(&path, "third\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\nthird\n");
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::Result;
+use indexmap::IndexSet;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// Gather `entrypoint` and the local files it references, breadth-first.
+///
+/// `max_depth` limits how many reference hops are followed (`None` follows the full closure).
+/// Only files inside `repo_root` are included; unreadable files are kept but not followed.
+pub fn collect_dependencies(
+    entrypoint: &Path,
+    repo_root: &Path,
+    max_depth: Option<usize>,
+) -> Result<Vec<PathBuf>> {
+    if !entrypoint.is_file() {
+        anyhow::bail!("entrypoint '{}' is not a file", entrypoint.display());
+    }
+
+    let entrypoint = normalize(entrypoint);
+    let mut ordered = IndexSet::new();
+    let mut queue = VecDeque::new();
+    ordered.insert(entrypoint.clone());
+    queue.push_back((entrypoint, 0));
+
+    while let Some((path, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for dependency in local_references(&path, &contents, repo_root) {
+            if dependency.starts_with(repo_root) && ordered.insert(dependency.clone()) {
+                queue.push_back((dependency, depth + 1));
+            }
+        }
+    }
+
+    Ok(ordered.into_iter().collect())
+}
+
+/// Resolve the local files referenced by `path`, based on its language.
+fn local_references(path: &Path, contents: &str, repo_root: &Path) -> Vec<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    let candidates: Vec<PathBuf> = match extension {
+        "rs" => rust_references(path, contents),
+        "py" => python_references(dir, contents, repo_root),
+        "rb" => quoted_references(contents, "require_relative")
+            .into_iter()
+            .map(|spec| with_default_extension(dir.join(spec), "rb"))
+            .collect(),
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" => quoted_references(contents, "#include")
+            .into_iter()
+            .map(|spec| dir.join(spec))
+            .collect(),
+        ext if JS_EXTENSIONS.contains(&ext) => js_references(dir, contents),
+        _ => Vec::new(),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.is_file())
+        .map(|candidate| normalize(&candidate))
+        .collect()
+}
+
+fn rust_references(path: &Path, contents: &str) -> Vec<PathBuf> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let module_dir = match stem {
+        "main" | "lib" | "mod" => parent.to_path_buf(),
+        _ => parent.join(stem),
+    };
+
+    let mut references = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        let line = line
+            .strip_prefix("pub(crate) ")
+            .or_else(|| line.strip_prefix("pub "))
+            .unwrap_or(line);
+        let Some(name) = line
+            .strip_prefix("mod ")
+            .and_then(|rest| rest.strip_suffix(';'))
+        else {
+            continue;
+        };
+
+        let name = name.trim();
+        let file = module_dir.join(format!("{name}.rs"));
+        if file.is_file() {
+            references.push(file);
+        } else {
+            references.push(module_dir.join(name).join("mod.rs"));
+        }
+    }
+    references
+}
+
+fn js_references(dir: &Path, contents: &str) -> Vec<PathBuf> {
+    let mut references = Vec::new();
+    for line in contents.lines() {
+        let is_import = ["import", "export", "require(", "from "]
+            .iter()
+            .any(|keyword| line.contains(keyword));
+        if !is_import {
+            continue;
+        }
+
+        for spec in string_literals(line) {
+            if spec.starts_with("./") || spec.starts_with("../") {
+                references.extend(resolve_js_module(&dir.join(spec)));
+            }
+        }
+    }
+    references
+}
+
+fn resolve_js_module(base: &Path) -> Option<PathBuf> {
+    if base.is_file() {
+        return Some(base.to_path_buf());
+    }
+
+    JS_EXTENSIONS
+        .iter()
+        .map(|ext| with_extension_appended(base, ext))
+        .chain(
+            JS_EXTENSIONS
+                .iter()
+                .map(|ext| base.join(format!("index.{ext}"))),
+        )
+        .find(|candidate| candidate.is_file())
+}
+
+fn python_references(dir: &Path, contents: &str, repo_root: &Path) -> Vec<PathBuf> {
+    let mut references = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("from ") {
+            let Some((module, names)) = rest.split_once(" import ") else {
+                continue;
+            };
+            let module = module.trim();
+            let dots = module.chars().take_while(|c| *c == '.').count();
+            let base = if dots > 0 {
+                dir.ancestors().nth(dots - 1).unwrap_or(dir).to_path_buf()
+            } else {
+                repo_root.to_path_buf()
+            };
+            let module = &module[dots..];
+
+            if module.is_empty() {
+                for name in names.split(',') {
+                    let name = name.trim().trim_matches(|c| c == '(' || c == ')');
+                    let name = name.split_whitespace().next().unwrap_or_default();
+                    references.extend(resolve_python_module(&base, name));
+                }
+            } else {
+                references.extend(resolve_python_module(&base, module));
+            }
+        } else if let Some(rest) = line.strip_prefix("import ") {
+            for module in rest.split(',') {
+                let module = module.split_whitespace().next().unwrap_or_default();
+                references.extend(resolve_python_module(repo_root, module));
+                references.extend(resolve_python_module(dir, module));
+            }
+        }
+    }
+    references
+}
+
+fn resolve_python_module(base: &Path, module: &str) -> Option<PathBuf> {
+    if module.is_empty() {
+        return None;
+    }
+
+    let relative: PathBuf = module.split('.').collect();
+    let file = with_extension_appended(&base.join(&relative), "py");
+    if file.is_file() {
+        return Some(file);
+    }
+
+    let package = base.join(relative).join("__init__.py");
+    package.is_file().then_some(package)
+}
+
+/// Collect the first string literal on lines that start with `keyword`.
+fn quoted_references(contents: &str, keyword: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| line.trim_start().starts_with(keyword))
+        .filter_map(|line| string_literals(line).into_iter().next())
+        .collect()
+}
+
+/// Extract the contents of every `'...'`, `"..."`, or `` `...` `` literal on a line.
+fn string_literals(line: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if !matches!(ch, '"' | '\'' | '`') {
+            continue;
+        }
+
+        let literal: String = chars.by_ref().take_while(|c| *c != ch).collect();
+        literals.push(literal);
+    }
+    literals
+}
+
+fn with_default_extension(path: PathBuf, extension: &str) -> PathBuf {
+    if path.extension().is_some() {
+        path
+    } else {
+        path.with_extension(extension)
+    }
+}
+
+fn with_extension_appended(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Resolve `.` and `..` components lexically, without following symlinks.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn relative(files: &[PathBuf], root: &Path) -> Vec<String> {
+        files
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn follows_rust_modules_breadth_first() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root.join("src/main.rs"), "mod config;\nmod gather;\n");
+        write(root.join("src/config.rs"), "pub mod schema;\n");
+        write(root.join("src/config/schema.rs"), "");
+        write(root.join("src/gather/mod.rs"), "");
+        write(root.join("src/unused.rs"), "");
+
+        let files = collect_dependencies(&root.join("src/main.rs"), root, None).unwrap();
+        assert_eq!(
+            relative(&files, root),
+            vec![
+                "src/main.rs",
+                "src/config.rs",
+                "src/gather/mod.rs",
+                "src/config/schema.rs"
+            ]
+        );
+
+        let shallow = collect_dependencies(&root.join("src/main.rs"), root, Some(1)).unwrap();
+        assert_eq!(shallow.len(), 3);
+    }
+
+    #[test]
+    fn follows_js_and_python_relative_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root.join("web/app.ts"),
+            "import { a } from './a';\nimport React from 'react';\nconst b = require(\"../lib\");\n",
+        );
+        write(root.join("web/a.tsx"), "");
+        write(root.join("lib/index.js"), "");
+        write(
+            root.join("pkg/main.py"),
+            "from .util import helper\nimport os\n",
+        );
+        write(root.join("pkg/util.py"), "");
+
+        let files = collect_dependencies(&root.join("web/app.ts"), root, None).unwrap();
+        assert_eq!(
+            relative(&files, root),
+            vec!["web/app.ts", "web/a.tsx", "lib/index.js"]
+        );
+
+        let files = collect_dependencies(&root.join("pkg/main.py"), root, None).unwrap();
+        assert_eq!(relative(&files, root), vec!["pkg/main.py", "pkg/util.py"]);
+    }
+}
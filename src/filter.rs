@@ -0,0 +1,165 @@
+use crate::gather::{
+    SizeLimits, SizeTrim, SortMode, TestFilter, apply_size_limits, filter_by_content, filter_empty,
+    filter_tests, sort_files,
+};
+use crate::since;
+use crate::warnings::Warnings;
+use anyhow::Result;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Filters applied to a run's files after its target is resolved, in the order
+/// [`filter_files`] runs them.
+#[derive(Debug, Default)]
+pub struct Filters {
+    /// Keep only, or leave out, files matching `test_patterns`.
+    pub tests: Option<TestFilter>,
+    pub test_patterns: Vec<String>,
+    /// Keep only files modified after every one of these times.
+    pub modified_after: Vec<SystemTime>,
+    /// Keep only files whose contents match.
+    pub grep: Option<Regex>,
+    /// Leave out empty and whitespace-only files.
+    pub skip_empty: bool,
+    pub sort: Option<SortMode>,
+    pub reverse: bool,
+    /// Keep a README first when sorting.
+    pub pin_readme: bool,
+    pub size_limits: SizeLimits,
+}
+
+/// Files left after the filters, with what each filter dropped for the summary.
+#[derive(Debug, Default)]
+pub struct Filtered {
+    pub files: Vec<PathBuf>,
+    pub grep_dropped: usize,
+    pub empty_dropped: usize,
+    pub trim: SizeTrim,
+}
+
+impl Filtered {
+    /// Append what the size limits, `--grep`, and `--skip-empty` left out to `summary`.
+    pub fn notes(&self, summary: String) -> String {
+        let summary = match (self.trim.oversized, self.trim.over_total) {
+            (0, 0) => summary,
+            (oversized, over_total) => format!(
+                "{summary}; truncated: {oversized} files over --max-file-size, {over_total} past --max-total-size"
+            ),
+        };
+        let summary = match self.grep_dropped {
+            0 => summary,
+            dropped => format!("{summary}; {dropped} files without a --grep match left out"),
+        };
+        match self.empty_dropped {
+            0 => summary,
+            dropped => format!("{summary}; {dropped} empty files skipped"),
+        }
+    }
+}
+
+/// Apply the test, modification-time, `--grep`, and `--skip-empty` filters, then the sort,
+/// then the size limits.
+pub fn filter_files(
+    files: Vec<PathBuf>,
+    filters: &Filters,
+    repo_root: &Path,
+    warnings: &mut Warnings,
+) -> Result<Filtered> {
+    let mut files = match filters.tests {
+        Some(filter) => filter_tests(files, repo_root, &filters.test_patterns, filter)?,
+        None => files,
+    };
+    for since in &filters.modified_after {
+        files = since::modified_after(files, *since, warnings);
+    }
+    let (files, grep_dropped) = match &filters.grep {
+        Some(pattern) => filter_by_content(files, pattern, repo_root, warnings),
+        None => (files, 0),
+    };
+    let (mut files, empty_dropped) = if filters.skip_empty {
+        filter_empty(files)
+    } else {
+        (files, 0)
+    };
+    if let Some(mode) = filters.sort {
+        sort_files(&mut files, mode, filters.reverse, filters.pin_readme);
+    }
+
+    let (files, trim) = apply_size_limits(files, repo_root, filters.size_limits, warnings);
+    Ok(Filtered {
+        files,
+        grep_dropped,
+        empty_dropped,
+        trim,
+    })
+}
+
+/// Refuse to gather more than `--max-files` files, so a too-broad target fails fast.
+pub fn check_max_files(count: usize, max_files: Option<usize>, description: &str) -> Result<()> {
+    match max_files {
+        Some(limit) if count > limit => anyhow::bail!(
+            "{count} files matched {description}, over the --max-files limit of {limit}; \
+             narrow the target or raise the limit"
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn notes_list_what_each_filter_dropped() {
+        let mut filtered = Filtered::default();
+        assert_eq!(filtered.notes("Copied".to_string()), "Copied");
+
+        filtered.grep_dropped = 2;
+        filtered.empty_dropped = 1;
+        filtered.trim.oversized = 3;
+        assert_eq!(
+            filtered.notes("Copied".to_string()),
+            "Copied; truncated: 3 files over --max-file-size, 0 past --max-total-size; 2 files without a --grep match left out; 1 empty files skipped"
+        );
+    }
+
+    #[test]
+    fn filter_files_applies_each_filter_in_order() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let files: Vec<PathBuf> = ["a.rs", "b_test.rs", "empty.rs", "todo.rs"]
+            .iter()
+            .map(|name| root.join(name))
+            .collect();
+        for (path, contents) in files.iter().zip(["fn a() {}", "TODO", "", "// TODO"]) {
+            fs::write(path, contents).unwrap();
+        }
+
+        let filters = Filters {
+            tests: Some(TestFilter::Exclude),
+            test_patterns: vec!["**/*_test.rs".to_string()],
+            grep: Some(Regex::new("TODO|fn").unwrap()),
+            skip_empty: true,
+            sort: Some(SortMode::Size),
+            reverse: true,
+            ..Filters::default()
+        };
+        let filtered = filter_files(files, &filters, root, &mut Warnings::default()).unwrap();
+        assert_eq!(filtered.files, [root.join("a.rs"), root.join("todo.rs")]);
+        assert_eq!((filtered.grep_dropped, filtered.empty_dropped), (1, 0));
+    }
+
+    #[test]
+    fn check_max_files_reports_count_and_limit() {
+        assert!(check_max_files(200, Some(200), "preset 'web'").is_ok());
+        assert!(check_max_files(5000, None, "preset 'web'").is_ok());
+        let err = check_max_files(201, Some(200), "preset 'web'").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "201 files matched preset 'web', over the --max-files limit of 200; narrow the target or raise the limit"
+        );
+    }
+}
@@ -1,17 +1,140 @@
 use crate::config::Preset;
+use crate::transform::{self, LineEndings, LineLimit};
+use crate::warnings::Warnings;
 use anyhow::{Context, Result};
-use globset::{Glob, GlobSet, GlobSetBuilder};
+use clap::ValueEnum;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use globwalk::GlobWalkerBuilder;
-use indexmap::IndexSet;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use indexmap::{IndexMap, IndexSet};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use walkdir::{DirEntry, WalkDir};
 
-/// Directories skipped during recursive walks when gathering paths.
-const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+/// Directories skipped during recursive walks unless the config replaces the list.
+pub const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+
+/// Gitignore-syntax file at the repo root listing paths to never gather.
+pub const GATHERIGNORE_FILE_NAME: &str = ".gatherignore";
+
+/// Path patterns that identify test files for `--exclude-tests` / `--only-tests`.
+pub const DEFAULT_TEST_PATTERNS: &[&str] = &[
+    "**/test/**",
+    "**/tests/**",
+    "**/spec/**",
+    "**/__tests__/**",
+    "**/*_test.*",
+    "**/*.test.*",
+    "**/*_spec.*",
+    "**/*.spec.*",
+    "**/test_*.py",
+];
+
+/// Whether to keep or drop test files after collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFilter {
+    Exclude,
+    Only,
+}
+
+/// Options controlling recursive directory walks.
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Honor `.gitignore` files, `.git/info/exclude`, and the global gitignore (path walks only).
+    pub respect_gitignore: bool,
+    /// Walk into dotfiles and dot-directories (path walks only); `ignored_dirs` still applies.
+    pub include_hidden: bool,
+    /// Directory names skipped at any depth, for both path walks and presets.
+    pub ignored_dirs: Vec<String>,
+    /// Config-wide exclude globs, added to each preset's `exclude` and applied to path walks.
+    pub exclude: Vec<String>,
+    /// `--exclude` globs, checked after every config exclude so a `!` in the config can't
+    /// re-include what they skip.
+    pub cli_exclude: Vec<String>,
+    /// Rules from the repo's `.gatherignore`, for both path walks and presets.
+    pub gatherignore: Option<Gitignore>,
+    /// Move the preferred README to the front of the collected files.
+    pub promote_readme: bool,
+    /// Base directory that replaces each preset's resolved `base` (presets only).
+    pub base: Option<PathBuf>,
+    /// Descend into symlinked directories; symlink cycles are detected and skipped.
+    pub follow_links: bool,
+    /// Note each preset's base and per-pattern match counts in the warnings (`--verbose`).
+    pub verbose: bool,
+    /// Fail when any preset include pattern matches nothing, instead of warning.
+    pub strict: bool,
+    /// Deepest level to walk below the target directory or preset base; 1 keeps only its
+    /// immediate children.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            respect_gitignore: true,
+            include_hidden: false,
+            ignored_dirs: IGNORED_DIRS.iter().map(|dir| dir.to_string()).collect(),
+            exclude: Vec::new(),
+            cli_exclude: Vec::new(),
+            gatherignore: None,
+            promote_readme: true,
+            base: None,
+            follow_links: false,
+            verbose: false,
+            strict: false,
+            max_depth: None,
+        }
+    }
+}
+
+/// Load `.gatherignore` from `repo_root`, or `None` when the file doesn't exist.
+pub fn load_gatherignore(repo_root: &Path) -> Result<Option<Gitignore>> {
+    let path = repo_root.join(GATHERIGNORE_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let mut builder = GitignoreBuilder::new(repo_root);
+    if let Some(err) = builder.add(&path) {
+        return Err(err).with_context(|| format!("failed to read {}", path.display()));
+    }
+    let gitignore = builder
+        .build()
+        .with_context(|| format!("invalid pattern in {}", path.display()))?;
+    Ok(Some(gitignore))
+}
+
+/// Whether `.gatherignore` excludes `path` or one of its parent directories.
+fn is_gatherignored(gatherignore: Option<&Gitignore>, path: &Path, is_dir: bool) -> bool {
+    let Some(gitignore) = gatherignore else {
+        return false;
+    };
+    let Ok(relative) = path.strip_prefix(gitignore.path()) else {
+        return false;
+    };
+    gitignore
+        .matched_path_or_any_parents(relative, is_dir)
+        .is_ignore()
+}
 
 /// Collect files from a directory (or a single file) recursively.
-pub fn collect_from_path(path: &Path) -> Result<Vec<PathBuf>> {
+///
+/// Inside a git repository, `.gitignore` files (including nested ones and those in
+/// parent directories), `.git/info/exclude`, and the global gitignore are honored the
+/// way git applies them, unless `options.respect_gitignore` is off. Dotfiles and
+/// dot-directories below `path` are skipped unless `options.include_hidden` is set.
+/// Entries that can't be read during the walk are skipped with a warning.
+pub fn collect_from_path(
+    path: &Path,
+    options: &WalkOptions,
+    warnings: &mut Warnings,
+) -> Result<Vec<PathBuf>> {
     if !path.exists() {
         anyhow::bail!("path '{}' does not exist", path.display());
     }
@@ -20,33 +143,122 @@ pub fn collect_from_path(path: &Path) -> Result<Vec<PathBuf>> {
         return Ok(vec![path.to_path_buf()]);
     }
 
+    let excludes: Vec<String> = options
+        .exclude
+        .iter()
+        .chain(&options.cli_exclude)
+        .cloned()
+        .collect();
+    let exclude = build_globset(&excludes)?;
     let mut files = Vec::new();
-    let walker = WalkDir::new(path).into_iter();
-    for entry in walker.filter_entry(|e| !is_ignored_dir(e)) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
+    let walker = WalkBuilder::new(path)
+        .standard_filters(false)
+        .hidden(!options.include_hidden)
+        .follow_links(options.follow_links)
+        .max_depth(options.max_depth)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .git_global(options.respect_gitignore)
+        .parents(true)
+        .filter_entry({
+            let ignored_dirs = options.ignored_dirs.clone();
+            let gatherignore = options.gatherignore.clone();
+            let root = path.to_path_buf();
+            move |e| {
+                let is_dir = e.file_type().is_some_and(|file_type| file_type.is_dir());
+                !is_ignored_dir(e, &ignored_dirs)
+                    && !prunes_entry(&exclude, &root, e.path(), is_dir)
+                    && !is_gatherignored(gatherignore.as_ref(), e.path(), is_dir)
+            }
+        })
+        .build();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A link back to an ancestor: everything below it is already being walked.
+            Err(err) if is_walk_loop(&err) => continue,
+            Err(err) => {
+                warnings.push(format!("skipped unreadable entry: {err}"));
+                continue;
+            }
+        };
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
             files.push(entry.into_path());
         }
     }
 
     files.sort_unstable();
-    promote_readme(path, &mut files);
+    if options.promote_readme {
+        promote_readme(path, &mut files);
+    }
     Ok(files)
 }
 
 /// Collect files based on preset patterns.
-pub fn collect_from_preset(name: &str, preset: &Preset, repo_root: &Path) -> Result<Vec<PathBuf>> {
-    let base = resolve_base(preset, repo_root);
-    let exclude = build_globset(&preset.exclude)?;
-    let ignored_patterns = ignored_dir_globs();
+pub fn collect_from_preset(
+    name: &str,
+    preset: &Preset,
+    presets: &IndexMap<String, Preset>,
+    repo_root: &Path,
+    options: &WalkOptions,
+    warnings: &mut Warnings,
+) -> Result<Vec<PathBuf>> {
+    if !preset.presets.is_empty() {
+        return collect_from_group(name, preset, presets, repo_root, options, warnings);
+    }
+
+    let base = options
+        .base
+        .clone()
+        .unwrap_or_else(|| resolve_base(preset, repo_root));
+    let excludes: Vec<String> = options
+        .exclude
+        .iter()
+        .chain(&preset.exclude)
+        .chain(&options.cli_exclude)
+        .cloned()
+        .collect();
+    let exclude = build_globset(&excludes)?;
+    let ignored_patterns = ignored_dir_globs(&options.ignored_dirs);
     let mut ordered = IndexSet::new();
+    if options.verbose {
+        warnings.note(format!("preset '{name}' base {}", base.display()));
+    }
+
+    if let Some(list) = &preset.from_file {
+        let listed = read_file_list(name, &repo_root.join(list), &base)?;
+        if options.verbose {
+            warnings.note(format!(
+                "  from_file {} listed {} files",
+                list.display(),
+                listed.len()
+            ));
+        }
+        ordered.extend(listed);
+    }
 
     for pattern in &preset.include {
-        let pattern_matches =
-            collect_pattern_matches(name, pattern, &base, &exclude, &ignored_patterns)?;
+        let mut pattern_matches =
+            collect_pattern_matches(name, pattern, &base, &exclude, &ignored_patterns, options)?;
+        pattern_matches
+            .retain(|path| !is_gatherignored(options.gatherignore.as_ref(), path, false));
+        if options.verbose {
+            warnings.note(format!(
+                "  include '{pattern}' matched {} files",
+                pattern_matches.len()
+            ));
+        }
 
         if pattern_matches.is_empty() {
-            anyhow::bail!("no files matched pattern '{pattern}' in preset '{name}'");
+            if options.strict {
+                anyhow::bail!("no files matched pattern '{pattern}' in preset '{name}'");
+            }
+            warnings.push(format!(
+                "no files matched pattern '{pattern}' in preset '{name}'"
+            ));
         }
 
         for path in pattern_matches {
@@ -54,306 +266,2770 @@ pub fn collect_from_preset(name: &str, preset: &Preset, repo_root: &Path) -> Res
         }
     }
 
+    if ordered.is_empty() {
+        anyhow::bail!("no files matched any pattern in preset '{name}'");
+    }
+
     let mut files: Vec<PathBuf> = ordered.into_iter().collect();
-    promote_readme(&base, &mut files);
+    // A hand-ordered list stays exactly as written.
+    if options.promote_readme && preset.from_file.is_none() {
+        promote_readme(&base, &mut files);
+    }
     Ok(files)
 }
 
-/// Render file contents in the gather_files format.
-pub fn render_files(files: &[PathBuf], root: &Path) -> Result<(String, usize)> {
-    let mut output = String::new();
-    let mut char_count = 0;
+/// Union the files of a group preset's members, in member order. Requires a validated,
+/// acyclic config.
+fn collect_from_group(
+    name: &str,
+    preset: &Preset,
+    presets: &IndexMap<String, Preset>,
+    repo_root: &Path,
+    options: &WalkOptions,
+    warnings: &mut Warnings,
+) -> Result<Vec<PathBuf>> {
+    let mut ordered = IndexSet::new();
+    for member in &preset.presets {
+        let member_preset = presets
+            .get(member)
+            .ok_or_else(|| anyhow::anyhow!("preset '{name}' lists unknown preset '{member}'"))?;
+        ordered.extend(collect_from_preset(
+            member,
+            member_preset,
+            presets,
+            repo_root,
+            options,
+            warnings,
+        )?);
+    }
+    Ok(ordered.into_iter().collect())
+}
 
-    for path in files {
-        let display = display_path(path, root);
-        let contents = fs::read_to_string(path)
-            .with_context(|| format!("failed to read {}", path.display()))?;
+/// Check that a preset's base and `from_file` exist and that every include pattern builds
+/// as a glob walker, without walking. Group presets have nothing of their own to check.
+pub fn preset_problems(preset: &Preset, repo_root: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !preset.presets.is_empty() {
+        return problems;
+    }
+
+    let base = resolve_base(preset, repo_root);
+    if !base.is_dir() {
+        problems.push(format!("base {} is not a directory", base.display()));
+    }
+    if let Some(list) = &preset.from_file
+        && !repo_root.join(list).is_file()
+    {
+        problems.push(format!(
+            "from_file {} does not exist",
+            repo_root.join(list).display()
+        ));
+    }
+    if base.is_dir() {
+        for pattern in &preset.include {
+            if let Err(err) = GlobWalkerBuilder::from_patterns(&base, &[pattern]).build() {
+                problems.push(format!("invalid glob '{pattern}': {err}"));
+            }
+        }
+    }
+    if let Err(err) = build_globset(&preset.exclude) {
+        problems.push(format!("{err:#}"));
+    }
+    problems
+}
 
-        char_count += append_file_section(&mut output, &display, &contents);
+/// Read a preset's `from_file` list: one path per line, relative to `base`, in order.
+///
+/// Blank lines and `#` comments are skipped; every listed path must be an existing file.
+fn read_file_list(name: &str, list: &Path, base: &Path) -> Result<Vec<PathBuf>> {
+    let contents = fs::read_to_string(list).with_context(|| {
+        format!(
+            "failed to read file list {} for preset '{name}'",
+            list.display()
+        )
+    })?;
+
+    let mut files = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = base.join(line);
+        if !path.is_file() {
+            anyhow::bail!(
+                "file '{line}' listed in {} for preset '{name}' does not exist: {}",
+                list.display(),
+                path.display()
+            );
+        }
+        files.push(path);
     }
+    Ok(files)
+}
+
+/// Remove files that resolve to the same canonical path, keeping the first occurrence.
+///
+/// Files that cannot be canonicalized are keyed on their path as given.
+pub fn dedup_files(files: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::with_capacity(files.len());
+    files
+        .into_iter()
+        .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect()
+}
 
-    Ok((output, char_count))
+/// Drop (or keep only) files whose repo-relative path matches a test pattern.
+pub fn filter_tests(
+    files: Vec<PathBuf>,
+    repo_root: &Path,
+    patterns: &[String],
+    filter: TestFilter,
+) -> Result<Vec<PathBuf>> {
+    let set = build_globset(patterns)?;
+    let keep_tests = filter == TestFilter::Only;
+    Ok(files
+        .into_iter()
+        .filter(|path| matches_exclude(&set, repo_root, path) == keep_tests)
+        .collect())
 }
 
-fn append_file_section(output: &mut String, display: &str, contents: &str) -> usize {
-    const HEADER_PREFIX: &str = "-------\n# ";
-    const HEADER_SUFFIX: &str = "\n\n";
+/// Keep only files whose contents match `pattern`, returning them and how many were dropped.
+///
+/// Binary files never match; unreadable files are dropped with a warning.
+pub fn filter_by_content(
+    files: Vec<PathBuf>,
+    pattern: &Regex,
+    root: &Path,
+    warnings: &mut Warnings,
+) -> (Vec<PathBuf>, usize) {
+    let total = files.len();
+    let kept: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| match fs::read(path) {
+            Ok(bytes) => {
+                !transform::is_binary(&bytes) && pattern.is_match(&String::from_utf8_lossy(&bytes))
+            }
+            Err(err) => {
+                warnings.push(format!(
+                    "skipped {} for --grep: {err}",
+                    display_path(path, root)
+                ));
+                false
+            }
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
 
-    output.reserve(HEADER_PREFIX.len() + display.len() + HEADER_SUFFIX.len() + contents.len() + 2);
+/// Drop zero-byte and whitespace-only files, returning the rest and how many were dropped.
+///
+/// Files that can't be read are kept so rendering reports them as usual.
+pub fn filter_empty(files: Vec<PathBuf>) -> (Vec<PathBuf>, usize) {
+    let total = files.len();
+    let kept: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| match fs::metadata(path) {
+            Ok(metadata) if metadata.len() == 0 => false,
+            Ok(_) => fs::read(path).map_or(true, |bytes| {
+                !bytes.iter().all(|byte| byte.is_ascii_whitespace())
+            }),
+            Err(_) => true,
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
 
-    output.push_str(HEADER_PREFIX);
-    output.push_str(display);
-    output.push_str(HEADER_SUFFIX);
-    output.push_str(contents);
+/// Byte limits applied to the gathered file list before rendering.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SizeLimits {
+    /// Skip any single file larger than this.
+    pub max_file: Option<u64>,
+    /// Stop gathering once the files so far would exceed this in total.
+    pub max_total: Option<u64>,
+}
 
-    let mut count = HEADER_PREFIX.len();
-    count += display.chars().count();
-    count += HEADER_SUFFIX.len();
-    count += contents.chars().count();
+/// How many files the size limits removed, and a marker for each drop.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SizeTrim {
+    pub oversized: usize,
+    pub over_total: usize,
+    pub omissions: Vec<Omission>,
+}
 
-    if !contents.ends_with('\n') {
-        output.push('\n');
-        count += 1;
+/// Content left out before rendering, marked in the bundle as `[gf: skipped <what> — <reason>]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Omission {
+    pub what: String,
+    pub reason: String,
+}
+
+/// Drop files over the per-file limit, then stop at the first file that would push the
+/// running total past the total limit. Each drop is recorded as a warning.
+///
+/// Files whose size can't be read are skipped and marked like any other unreadable file.
+pub fn apply_size_limits(
+    files: Vec<PathBuf>,
+    root: &Path,
+    limits: SizeLimits,
+    warnings: &mut Warnings,
+) -> (Vec<PathBuf>, SizeTrim) {
+    if limits.max_file.is_none() && limits.max_total.is_none() {
+        return (files, SizeTrim::default());
     }
-    output.push('\n');
-    count += 1;
 
-    count
+    let mut trim = SizeTrim::default();
+    let mut kept = Vec::with_capacity(files.len());
+    let mut total: u64 = 0;
+    for (idx, path) in files.iter().enumerate() {
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                skip_file(path, root, err.to_string(), &mut trim, warnings);
+                continue;
+            }
+        };
+        if let Some(max) = limits.max_file
+            && size > max
+        {
+            let reason = format!("{size} bytes exceeds --max-file-size ({max} bytes)");
+            skip_file(path, root, reason, &mut trim, warnings);
+            trim.oversized += 1;
+            continue;
+        }
+
+        if let Some(max) = limits.max_total
+            && total + size > max
+        {
+            trim.over_total = files.len() - idx;
+            warnings.push(format!(
+                "stopped at {}: --max-total-size ({max} bytes) reached, {} files dropped",
+                display_path(path, root),
+                trim.over_total
+            ));
+            trim.omissions.push(Omission {
+                what: format!(
+                    "{} files starting at {}",
+                    trim.over_total,
+                    display_path(path, root)
+                ),
+                reason: format!("--max-total-size ({max} bytes) reached"),
+            });
+            break;
+        }
+
+        total += size;
+        kept.push(path.clone());
+    }
+
+    (kept, trim)
 }
 
-fn display_path(path: &Path, root: &Path) -> String {
-    if let Ok(relative) = path.strip_prefix(root) {
-        if relative.as_os_str().is_empty() {
-            return path.display().to_string();
+/// Warn about a file left out for `reason` and mark it in the bundle.
+fn skip_file(
+    path: &Path,
+    root: &Path,
+    reason: String,
+    trim: &mut SizeTrim,
+    warnings: &mut Warnings,
+) {
+    let omission = Omission {
+        what: display_path(path, root),
+        reason,
+    };
+    warnings.push(format!("skipped {}: {}", omission.what, omission.reason));
+    trim.omissions.push(omission);
+}
+
+/// Output shape for rendered bundles.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `-------` separated sections with `# path` headers.
+    #[default]
+    Text,
+    /// `## path` headers with fenced code blocks.
+    Markdown,
+    /// `<file path="...">` elements.
+    Xml,
+    /// A JSON array of `{ "path", "content" }` objects.
+    Json,
+}
+
+/// Section header for the text format: `separator`, then `header` with `{path}` replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SectionTemplate {
+    /// Line introducing each file, with `{path}` replaced by its display path.
+    pub header: String,
+    /// Text written before each header.
+    pub separator: String,
+}
+
+impl Default for SectionTemplate {
+    fn default() -> Self {
+        Self {
+            header: "# {path}".to_string(),
+            separator: "-------\n".to_string(),
         }
-        return relative.display().to_string();
     }
+}
 
-    path.display().to_string()
+/// Options controlling how gathered files are rendered.
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    /// Output shape for the bundle.
+    pub format: OutputFormat,
+    /// Collapse leading import blocks into a single omission line.
+    pub trim_imports: bool,
+    /// Prefix each content line with its line number.
+    pub line_numbers: bool,
+    /// Keep only each file's first or last lines (`--head`/`--tail`).
+    pub line_limit: Option<LineLimit>,
+    /// Drop comment lines, chosen by file extension.
+    pub strip_comments: bool,
+    /// Extensions whose comments `strip_comments` leaves alone.
+    pub keep_comments: Vec<String>,
+    /// Shell commands that transform file contents, keyed by file extension.
+    pub content_filters: IndexMap<String, String>,
+    /// Render binary files as base64 instead of skipping them.
+    pub include_binary: bool,
+    /// Suppress `[gf: skipped ...]` markers wherever content is left out.
+    pub no_annotations: bool,
+    /// Line-ending normalization applied to each file's contents.
+    pub line_endings: LineEndings,
+    /// Wrap the whole bundle in a single fenced code block.
+    pub wrap_all: bool,
+    /// Prepend an ASCII tree of the gathered paths.
+    pub tree: bool,
+    /// Prepend a numbered list of files with each section's char count.
+    pub toc: bool,
+    /// Header layout for text-format sections.
+    pub template: SectionTemplate,
+    /// Render files whose contents repeat an earlier file as a reference header only.
+    pub dedup_content: bool,
+    /// Files dropped before rendering (such as by size limits), marked after the gathered files.
+    pub omissions: Vec<Omission>,
+    /// Content not backed by a file on disk, rendered after the gathered files.
+    pub virtual_files: Vec<VirtualFile>,
+    /// Text placed verbatim before the whole bundle (`--prepend`).
+    pub prepend: Option<String>,
+    /// Text placed verbatim after the whole bundle (`--append-file`).
+    pub append: Option<String>,
 }
 
-fn resolve_base(preset: &Preset, repo_root: &Path) -> PathBuf {
-    match &preset.base {
-        Some(base) if base.is_absolute() => base.clone(),
-        Some(base) => repo_root.join(base),
-        None => repo_root.to_path_buf(),
+/// Ad-hoc content (such as piped stdin) rendered as if it were a file.
+#[derive(Debug, Clone)]
+pub struct VirtualFile {
+    pub name: String,
+    pub contents: String,
+}
+
+/// A single rendered file section and its character count.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub display: String,
+    pub text: String,
+    pub chars: usize,
+    /// The section is only a `[gf: skipped ...]` marker; the file's content was left out.
+    pub skipped: bool,
+}
+
+impl Section {
+    pub fn size(&self) -> SectionSize {
+        SectionSize {
+            display: self.display.clone(),
+            chars: self.chars,
+            skipped: self.skipped,
+        }
     }
 }
 
-fn ignored_dir_globs() -> Vec<String> {
-    IGNORED_DIRS
-        .iter()
-        .map(|dir| format!("!**/{dir}/"))
-        .collect()
+/// A section's display path and character count, without its text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionSize {
+    pub display: String,
+    pub chars: usize,
+    /// The section is only a `[gf: skipped ...]` marker.
+    pub skipped: bool,
 }
 
-fn build_preset_patterns<'a>(pattern: &'a str, ignored: &'a [String]) -> Vec<&'a str> {
-    let mut patterns = Vec::with_capacity(1 + ignored.len());
-    patterns.push(pattern);
-    patterns.extend(ignored.iter().map(String::as_str));
-    patterns
+/// How many sections hold a file's content rather than a skip marker.
+pub fn count_rendered(sections: &[SectionSize]) -> usize {
+    sections.iter().filter(|section| !section.skipped).count()
 }
 
-fn collect_pattern_matches(
-    preset_name: &str,
-    pattern: &str,
-    base: &Path,
-    exclude: &Option<GlobSet>,
-    ignored_patterns: &[String],
-) -> Result<Vec<PathBuf>> {
-    let patterns = build_preset_patterns(pattern, ignored_patterns);
-    let walker = GlobWalkerBuilder::from_patterns(base, &patterns)
-        .follow_links(false)
-        .build()
-        .with_context(|| format!("invalid glob '{pattern}' in preset '{preset_name}'"))?;
+/// A rendered bundle, its character count, and the size of each section in it.
+#[derive(Debug)]
+pub struct Rendered {
+    pub output: String,
+    pub chars: usize,
+    pub sections: Vec<SectionSize>,
+}
 
-    let mut matches = Vec::new();
-    for entry in walker {
-        let entry = entry?;
-        if entry.file_type().is_dir() {
-            continue;
+/// Render file contents in the requested output format.
+///
+/// Unreadable files are skipped and invalid UTF-8 is replaced, each with a warning.
+pub fn render_files(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<(String, usize)> {
+    let rendered = render_bundle(files, root, options, warnings)?;
+    Ok((rendered.output, rendered.chars))
+}
+
+/// Like [`render_files`], but also report each section's size. For JSON output, a
+/// section's size is its file's content length.
+pub fn render_bundle(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<Rendered> {
+    let mut header = String::new();
+    let mut header_chars = 0;
+    let section_sizes;
+    let (body, body_chars) = if options.format == OutputFormat::Json {
+        for (flag, set) in [
+            ("--toc", options.toc),
+            ("--tree", options.tree),
+            ("--wrap-all", options.wrap_all),
+            ("--prepend", options.prepend.is_some()),
+            ("--append-file", options.append.is_some()),
+        ] {
+            if set {
+                warnings.push(format!("{flag} is ignored for JSON output"));
+            }
+        }
+        let (output, char_count, sizes) = render_json(files, root, options, warnings)?;
+        section_sizes = sizes;
+        (output, char_count)
+    } else {
+        let sections = render_sections(files, root, options, warnings)?;
+        section_sizes = sections.iter().map(Section::size).collect();
+        if options.toc {
+            header_chars += append_toc_header(&mut header, &sections, options);
+        }
+        if options.tree {
+            header_chars += append_tree_header(&mut header, &render_tree(files, root), options);
         }
 
-        let path = entry.into_path();
-        if matches_exclude(exclude, base, &path) {
-            continue;
+        let mut output = String::with_capacity(sections.iter().map(|s| s.text.len()).sum());
+        let mut char_count = 0;
+        for section in sections {
+            output.push_str(&section.text);
+            char_count += section.chars;
         }
+        (output, char_count)
+    };
 
-        matches.push(path);
-    }
+    header.push_str(&body);
+    let (output, char_count) = (header, header_chars + body_chars);
 
-    matches.sort_unstable();
-    Ok(matches)
+    if options.format == OutputFormat::Json {
+        return Ok(Rendered {
+            output,
+            chars: char_count,
+            sections: section_sizes,
+        });
+    }
+    let (mut output, mut char_count) = if options.wrap_all {
+        wrap_in_fence(&output, char_count)
+    } else {
+        (output, char_count)
+    };
+    if let Some(prepend) = &options.prepend {
+        char_count += prepend.chars().count();
+        output.insert_str(0, prepend);
+    }
+    if let Some(append) = &options.append {
+        char_count += push_counted(&mut output, append);
+    }
+    Ok(Rendered {
+        output,
+        chars: char_count,
+        sections: section_sizes,
+    })
 }
 
-fn build_globset(patterns: &[String]) -> Result<Option<GlobSet>> {
-    if patterns.is_empty() {
-        return Ok(None);
+/// Append the `--toc` block: each section's path and char count, numbered in bundle order.
+fn append_toc_header(output: &mut String, sections: &[Section], options: &RenderOptions) -> usize {
+    let mut list = String::new();
+    let files = sections.iter().filter(|section| !section.skipped);
+    for (idx, section) in files.enumerate() {
+        list.push_str(&format!(
+            "{}. {} ({} chars)\n",
+            idx + 1,
+            section.display,
+            section.chars
+        ));
     }
 
-    let mut builder = GlobSetBuilder::new();
-    for pattern in patterns {
-        let glob = Glob::new(pattern)
-            .with_context(|| format!("invalid exclude glob pattern '{pattern}'"))?;
-        builder.add(glob);
+    match options.format {
+        OutputFormat::Markdown => {
+            let mut count = push_counted(output, "## Table of contents\n\n");
+            count += push_counted(output, &list);
+            count + push_counted(output, "\n")
+        }
+        OutputFormat::Xml => {
+            let mut count = push_counted(output, "<toc>\n");
+            count += push_counted(output, &escape_xml_attribute(&list));
+            count + push_counted(output, "</toc>\n\n")
+        }
+        OutputFormat::Text | OutputFormat::Json => {
+            append_file_section(output, "Table of contents", &list, &options.template)
+        }
     }
+}
 
-    let set = builder.build()?;
-    Ok(Some(set))
+/// Nested path components collected for `--tree`.
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
 }
 
-fn matches_exclude(set: &Option<GlobSet>, base: &Path, path: &Path) -> bool {
-    match set {
-        Some(set) => {
-            let candidate = path.strip_prefix(base).unwrap_or(path);
-            set.is_match(candidate)
+/// Render `files` as an ASCII tree like `tree`'s output, directories first and each level sorted.
+pub fn render_tree(files: &[PathBuf], root: &Path) -> String {
+    let mut tree = TreeNode::default();
+    for path in files {
+        let display = display_path(path, root);
+        let mut node = &mut tree;
+        for component in Path::new(&display).components() {
+            let name = component.as_os_str().to_string_lossy().into_owned();
+            node = node.children.entry(name).or_default();
         }
-        None => false,
     }
-}
 
-fn promote_readme(base: &Path, files: &mut Vec<PathBuf>) {
-    if files.len() <= 1 {
-        return;
-    }
+    let mut output = String::from(".\n");
+    push_tree_children(&mut output, &tree, "");
+    output
+}
 
-    if let Some(idx) = find_preferred_readme(base, files)
-        && idx != 0
-    {
-        let readme = files.remove(idx);
-        files.insert(0, readme);
+fn push_tree_children(output: &mut String, node: &TreeNode, prefix: &str) {
+    let (dirs, files): (Vec<_>, Vec<_>) = node
+        .children
+        .iter()
+        .partition(|(_, child)| !child.children.is_empty());
+    let entries: Vec<_> = dirs.into_iter().chain(files).collect();
+    for (idx, (name, child)) in entries.iter().enumerate() {
+        let last = idx + 1 == entries.len();
+        output.push_str(prefix);
+        output.push_str(if last { "└── " } else { "├── " });
+        output.push_str(name);
+        output.push('\n');
+        let indent = if last { "    " } else { "│   " };
+        push_tree_children(output, child, &format!("{prefix}{indent}"));
     }
 }
 
-fn find_preferred_readme(base: &Path, files: &[PathBuf]) -> Option<usize> {
-    let mut fallback = None;
-    for (idx, path) in files.iter().enumerate() {
-        if !is_readme(path) {
-            continue;
+/// Append the `--tree` block in the bundle's format, returning its char count.
+fn append_tree_header(output: &mut String, tree: &str, options: &RenderOptions) -> usize {
+    match options.format {
+        OutputFormat::Markdown => {
+            let mut count = push_counted(output, "## File tree\n\n```text\n");
+            count += push_counted(output, tree);
+            count + push_counted(output, "```\n\n")
         }
-
-        if is_direct_child(base, path) {
-            return Some(idx);
+        OutputFormat::Xml => {
+            let mut count = push_counted(output, "<tree>\n");
+            count += push_counted(output, &escape_xml_attribute(tree));
+            count + push_counted(output, "</tree>\n\n")
         }
-
-        if fallback.is_none() {
-            fallback = Some(idx);
+        OutputFormat::Text | OutputFormat::Json => {
+            append_file_section(output, "File tree", tree, &options.template)
         }
     }
-
-    fallback
 }
 
-fn is_direct_child(base: &Path, path: &Path) -> bool {
-    match path.strip_prefix(base) {
-        Ok(relative) => relative.components().count() == 1,
-        Err(_) => false,
+/// Wrap `output` in one fence longer than any backtick run it contains.
+fn wrap_in_fence(output: &str, char_count: usize) -> (String, usize) {
+    let fence = markdown_fence(output);
+    let mut wrapped = String::with_capacity(output.len() + fence.len() * 2 + 3);
+    let mut count = char_count;
+    count += push_counted(&mut wrapped, &fence);
+    count += push_counted(&mut wrapped, "\n");
+    wrapped.push_str(output);
+    if !output.ends_with('\n') {
+        count += push_counted(&mut wrapped, "\n");
     }
+    count += push_counted(&mut wrapped, &fence);
+    count += push_counted(&mut wrapped, "\n");
+    (wrapped, count)
 }
 
-fn is_readme(path: &Path) -> bool {
-    path.file_name()
-        .and_then(|name| name.to_str())
-        .map(|name| name.to_ascii_lowercase().starts_with("readme"))
-        .unwrap_or(false)
-}
+/// Render each file into its own section so callers can pack them into parts.
+///
+/// JSON output is a single document and cannot be split into sections.
+pub fn render_sections(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<Vec<Section>> {
+    if options.format == OutputFormat::Json {
+        anyhow::bail!("JSON output cannot be rendered as separate sections");
+    }
 
-fn is_ignored_dir(entry: &DirEntry) -> bool {
-    if !entry.file_type().is_dir() {
-        return false;
+    let entries = load_entries(files, root, options, warnings)?;
+    let mut sections = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let mut text = String::new();
+        let chars = match (&entry.skipped, &entry.duplicate_of, options.format) {
+            (Some(reason), _, _) => {
+                let marker = transform::skip_annotation(&entry.display, reason);
+                push_counted(&mut text, &marker) + push_counted(&mut text, "\n\n")
+            }
+            (None, Some(original), format) => append_duplicate_section(
+                &mut text,
+                &entry.display,
+                original,
+                format,
+                &options.template,
+            ),
+            (None, None, OutputFormat::Markdown) => {
+                append_markdown_section(&mut text, &entry.path, &entry.display, &entry.contents)
+            }
+            (None, None, OutputFormat::Xml) => {
+                append_xml_section(&mut text, &entry.display, &entry.contents)
+            }
+            (None, None, OutputFormat::Text | OutputFormat::Json) => append_file_section(
+                &mut text,
+                &entry.display,
+                &entry.contents,
+                &options.template,
+            ),
+        };
+        sections.push(Section {
+            display: entry.display.clone(),
+            text,
+            chars,
+            skipped: entry.skipped.is_some(),
+        });
     }
 
-    let name = entry.file_name();
-    let name = match name.to_str() {
-        Some(name) => name,
-        None => return false,
-    };
+    Ok(sections)
+}
 
-    IGNORED_DIRS.contains(&name)
+/// A file's display name, source path, and transformed contents, ready to render.
+struct Entry {
+    display: String,
+    path: PathBuf,
+    contents: String,
+    /// Contents are a binary file, base64-encoded by `--include-binary`.
+    base64: bool,
+    /// Display path of an earlier entry with identical contents (`--dedup-content`).
+    duplicate_of: Option<String>,
+    /// Why the contents were left out; the entry renders as a `[gf: skipped ...]` marker.
+    skipped: Option<String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::ConfigFile;
-    use std::io::Write;
-    use tempfile::tempdir;
+/// Below this many files, reading in parallel costs more in thread overhead than it saves.
+const PARALLEL_READ_THRESHOLD: usize = 16;
 
-    #[test]
-    fn promotes_readme_in_directory() {
-        let dir = tempdir().unwrap();
-        let path = dir.path();
-        write_file(path.join("README.md"), "# hi");
-        write_file(path.join("b.txt"), "b");
-        write_file(path.join("a.txt"), "a");
+/// Read and transform every file, then append any virtual files.
+///
+/// Large inputs are read in parallel; results and warnings are still assembled in `files`
+/// order, so the output is the same either way.
+fn load_entries(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<Vec<Entry>> {
+    let read = |path: &PathBuf| {
+        let display = display_path(path, root);
+        let mut file_warnings = Warnings::default();
+        let contents = read_contents(path, &display, options, &mut file_warnings);
+        (display, contents, file_warnings)
+    };
+    let results: Vec<_> = if files.len() < PARALLEL_READ_THRESHOLD {
+        files.iter().map(read).collect()
+    } else {
+        files.par_iter().map(read).collect()
+    };
 
-        let files = collect_from_path(path).unwrap();
-        assert_eq!(
-            files
+    let mut entries = Vec::with_capacity(files.len() + options.virtual_files.len());
+    for (path, (display, contents, file_warnings)) in files.iter().zip(results) {
+        warnings.extend(file_warnings);
+        match contents? {
+            Ok((contents, base64)) => entries.push(Entry {
+                display,
+                path: path.clone(),
+                contents,
+                base64,
+                duplicate_of: None,
+                skipped: None,
+            }),
+            Err(reason) => {
+                warnings.push(format!("skipped {display}: {reason}"));
+                if !options.no_annotations {
+                    entries.push(skipped_entry(display, reason));
+                }
+            }
+        }
+    }
+    if !options.no_annotations {
+        entries.extend(
+            options
+                .omissions
                 .iter()
-                .map(|p| p.file_name().unwrap().to_str().unwrap())
-                .collect::<Vec<_>>(),
-            vec!["README.md", "a.txt", "b.txt"]
+                .map(|omission| skipped_entry(omission.what.clone(), omission.reason.clone())),
         );
     }
 
-    #[test]
-    fn collects_files_from_preset() {
-        let dir = tempdir().unwrap();
-        let base = dir.path();
-        write_file(base.join("README.md"), "root");
-        write_file(base.join("src/lib.rs"), "lib");
-        write_file(base.join("src/main.rs"), "main");
-        write_file(base.join("src/extra.txt"), "extra");
-
-        let config_yaml = r#"
-version: 1
-presets:
-  rust:
-    base: .
-    include:
-      - "src/**/*.rs"
-    exclude:
-      - "src/lib.rs"
-"#;
-        let config_path = base.join(".gather-files.yaml");
-        fs::write(&config_path, config_yaml).unwrap();
-        let config = ConfigFile::load(&config_path).unwrap().unwrap();
-        let preset = config.preset("rust").unwrap();
-        let files = collect_from_preset("rust", preset, base).unwrap();
-        assert_eq!(
-            files
-                .iter()
-                .map(|p| p.strip_prefix(base).unwrap().display().to_string())
-                .collect::<Vec<_>>(),
-            vec!["src/main.rs"]
-        );
+    for file in &options.virtual_files {
+        let path = PathBuf::from(&file.name);
+        entries.push(Entry {
+            display: file.name.clone(),
+            contents: transform_contents(&path, file.contents.clone(), options),
+            path,
+            base64: false,
+            duplicate_of: None,
+            skipped: None,
+        });
+    }
+
+    if options.dedup_content {
+        mark_duplicates(&mut entries);
+    }
+    Ok(entries)
+}
+
+/// An entry that renders only as a marker saying why `display` was left out.
+fn skipped_entry(display: String, reason: String) -> Entry {
+    Entry {
+        path: PathBuf::from(&display),
+        display,
+        contents: String::new(),
+        base64: false,
+        duplicate_of: None,
+        skipped: Some(reason),
+    }
+}
+
+/// Point each entry whose contents match an earlier entry's at that first occurrence.
+fn mark_duplicates(entries: &mut [Entry]) {
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    for idx in 0..entries.len() {
+        if entries[idx].skipped.is_some() {
+            continue;
+        }
+        let mut hasher = DefaultHasher::new();
+        entries[idx].contents.hash(&mut hasher);
+        let candidates = seen.entry(hasher.finish()).or_default();
+        // Compare contents too, so a hash collision never drops a file.
+        let original = candidates
+            .iter()
+            .find(|&&earlier| entries[earlier].contents == entries[idx].contents)
+            .map(|&earlier| entries[earlier].display.clone());
+        match original {
+            Some(original) => entries[idx].duplicate_of = Some(original),
+            None => candidates.push(idx),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a str,
+    /// Left out for `--dedup-content` duplicates, which carry `identical_to` instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identical_to: Option<&'a str>,
+    /// Set to `"base64"` for binary files included with `--include-binary`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+}
+
+fn render_json(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<(String, usize, Vec<SectionSize>)> {
+    let entries = load_entries(files, root, options, warnings)?;
+    let records: Vec<JsonFile> = entries
+        .iter()
+        .filter(|entry| entry.skipped.is_none())
+        .map(|entry| JsonFile {
+            path: &entry.display,
+            content: entry
+                .duplicate_of
+                .is_none()
+                .then_some(entry.contents.as_str()),
+            identical_to: entry.duplicate_of.as_deref(),
+            encoding: entry.base64.then_some("base64"),
+        })
+        .collect();
+    let mut output =
+        serde_json::to_string_pretty(&records).context("failed to serialize JSON output")?;
+    output.push('\n');
+    let char_count = output.chars().count();
+    let sizes = records
+        .iter()
+        .map(|record| SectionSize {
+            display: record.path.to_string(),
+            chars: record.content.map_or(0, |content| content.chars().count()),
+            skipped: false,
+        })
+        .collect();
+    Ok((output, char_count, sizes))
+}
+
+/// Read a file's contents and whether they were base64-encoded, or the reason the file
+/// is skipped.
+fn read_contents(
+    path: &Path,
+    display: &str,
+    options: &RenderOptions,
+    warnings: &mut Warnings,
+) -> Result<Result<(String, bool), String>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => return Ok(Err(err.to_string())),
+    };
+    // Filters see the raw bytes, so they can turn binary formats into text.
+    if let Some(command) = content_filter_for(path, options) {
+        let contents = transform::run_content_filter(command, &bytes)
+            .with_context(|| format!("failed to filter {}", path.display()))?;
+        return Ok(Ok((transform_contents(path, contents, options), false)));
+    }
+
+    if transform::is_binary(&bytes) {
+        if !options.include_binary {
+            return Ok(Err("binary file".to_string()));
+        }
+        return Ok(Ok((transform::encode_base64(&bytes), true)));
+    }
+
+    let contents = match String::from_utf8(bytes) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warnings.push(format!(
+                "{display}: not valid UTF-8; invalid bytes were replaced"
+            ));
+            String::from_utf8_lossy(err.as_bytes()).into_owned()
+        }
+    };
+    Ok(Ok((transform_contents(path, contents, options), false)))
+}
+
+fn content_filter_for<'a>(path: &Path, options: &'a RenderOptions) -> Option<&'a str> {
+    let extension = path.extension()?.to_str()?;
+    options.content_filters.get(extension).map(String::as_str)
+}
+
+fn transform_contents(path: &Path, contents: String, options: &RenderOptions) -> String {
+    let contents =
+        transform::normalize_line_endings(&contents, options.line_endings).unwrap_or(contents);
+
+    let contents = if options.strip_comments
+        && let Some(stripped) = transform::strip_comments(path, &contents, &options.keep_comments)
+    {
+        stripped
+    } else {
+        contents
+    };
+
+    let contents = if options.trim_imports
+        && let Some(trimmed) = transform::trim_imports(path, &contents, !options.no_annotations)
+    {
+        trimmed
+    } else {
+        contents
+    };
+
+    let contents = if options.line_numbers {
+        transform::number_lines(&contents)
+    } else {
+        contents
+    };
+
+    // Last, so line numbers still count from the top of the original file.
+    if let Some(limit) = options.line_limit
+        && let Some(limited) = transform::limit_lines(&contents, limit, !options.no_annotations)
+    {
+        return limited;
+    }
+    contents
+}
+
+fn append_file_section(
+    output: &mut String,
+    display: &str,
+    contents: &str,
+    template: &SectionTemplate,
+) -> usize {
+    let mut count = push_counted(output, &template.separator);
+    count += push_counted(output, &template.header.replace("{path}", display));
+    count += push_counted(output, "\n\n");
+    count += push_counted(output, contents);
+    if !contents.ends_with('\n') {
+        count += push_counted(output, "\n");
+    }
+    count + push_counted(output, "\n")
+}
+
+fn append_markdown_section(
+    output: &mut String,
+    path: &Path,
+    display: &str,
+    contents: &str,
+) -> usize {
+    let fence = markdown_fence(contents);
+    let mut count = push_counted(output, "## ");
+    count += push_counted(output, display);
+    count += push_counted(output, "\n\n");
+    count += push_counted(output, &fence);
+    count += push_counted(output, markdown_language(path));
+    count += push_counted(output, "\n");
+    count += push_counted(output, contents);
+    if !contents.ends_with('\n') {
+        count += push_counted(output, "\n");
+    }
+    count += push_counted(output, &fence);
+    count += push_counted(output, "\n\n");
+    count
+}
+
+fn append_xml_section(output: &mut String, display: &str, contents: &str) -> usize {
+    let mut count = push_counted(output, "<file path=\"");
+    count += push_counted(output, &escape_xml_attribute(display));
+    count += push_counted(output, "\">\n");
+    // Escaped so a file containing `</file>` can't close its section early.
+    count += push_counted(output, &escape_xml_attribute(contents));
+    if !contents.ends_with('\n') {
+        count += push_counted(output, "\n");
+    }
+    count += push_counted(output, "</file>\n\n");
+    count
+}
+
+/// Append a header-only section pointing a duplicate file at its first occurrence.
+fn append_duplicate_section(
+    output: &mut String,
+    display: &str,
+    original: &str,
+    format: OutputFormat,
+    template: &SectionTemplate,
+) -> usize {
+    let note = format!(" (identical to {original})");
+    match format {
+        OutputFormat::Markdown => {
+            let mut count = push_counted(output, "## ");
+            count += push_counted(output, display);
+            count += push_counted(output, &note);
+            count + push_counted(output, "\n\n")
+        }
+        OutputFormat::Xml => {
+            let mut count = push_counted(output, "<file path=\"");
+            count += push_counted(output, &escape_xml_attribute(display));
+            count += push_counted(output, "\" identical-to=\"");
+            count += push_counted(output, &escape_xml_attribute(original));
+            count + push_counted(output, "\"/>\n\n")
+        }
+        OutputFormat::Text | OutputFormat::Json => {
+            let mut count = push_counted(output, &template.separator);
+            count += push_counted(output, &template.header.replace("{path}", display));
+            count += push_counted(output, &note);
+            count + push_counted(output, "\n\n")
+        }
+    }
+}
+
+fn push_counted(output: &mut String, text: &str) -> usize {
+    output.push_str(text);
+    text.chars().count()
+}
+
+/// Pick a backtick fence longer than any backtick run inside `contents`.
+pub fn markdown_fence(contents: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for ch in contents.chars() {
+        if ch == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+
+    "`".repeat((longest + 1).max(3))
+}
+
+/// Fence info string for a file, from well-known file names and then the extension.
+fn markdown_language(path: &Path) -> &'static str {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match file_name {
+        "Dockerfile" | "Containerfile" => return "dockerfile",
+        "Makefile" | "GNUmakefile" | "makefile" => return "makefile",
+        "CMakeLists.txt" => return "cmake",
+        "Gemfile" | "Rakefile" | "Podfile" | "Vagrantfile" => return "ruby",
+        _ => {}
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "rb" | "rake" | "gemspec" => "ruby",
+        "erb" => "erb",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "m" | "mm" => "objectivec",
+        "cs" => "csharp",
+        "php" => "php",
+        "ex" | "exs" => "elixir",
+        "erl" | "hrl" => "erlang",
+        "hs" => "haskell",
+        "lua" => "lua",
+        "pl" | "pm" => "perl",
+        "r" => "r",
+        "dart" => "dart",
+        "zig" => "zig",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sql" => "sql",
+        "graphql" | "gql" => "graphql",
+        "proto" => "protobuf",
+        "tf" | "hcl" => "hcl",
+        "md" | "markdown" => "markdown",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" | "svg" => "xml",
+        "ini" | "cfg" => "ini",
+        "sh" | "bash" | "zsh" => "bash",
+        "fish" => "fish",
+        "ps1" => "powershell",
+        "diff" | "patch" => "diff",
+        _ => "",
+    }
+}
+
+fn escape_xml_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Display `path` relative to `root` when possible, falling back to the full path.
+pub fn display_path(path: &Path, root: &Path) -> String {
+    if let Ok(relative) = path.strip_prefix(root) {
+        if relative.as_os_str().is_empty() {
+            return path.display().to_string();
+        }
+        return relative.display().to_string();
+    }
+
+    path.display().to_string()
+}
+
+/// Directory a preset's patterns are matched against: its `base`, relative to `repo_root`.
+pub fn resolve_base(preset: &Preset, repo_root: &Path) -> PathBuf {
+    match &preset.base {
+        Some(base) if base.is_absolute() => base.clone(),
+        Some(base) => repo_root.join(base),
+        None => repo_root.to_path_buf(),
+    }
+}
+
+fn ignored_dir_globs(ignored_dirs: &[String]) -> Vec<String> {
+    ignored_dirs
+        .iter()
+        .map(|dir| format!("!**/{dir}/"))
+        .collect()
+}
+
+fn build_preset_patterns<'a>(pattern: &'a str, ignored: &'a [String]) -> Vec<&'a str> {
+    let mut patterns = Vec::with_capacity(1 + ignored.len());
+    patterns.push(pattern);
+    patterns.extend(ignored.iter().map(String::as_str));
+    patterns
+}
+
+fn is_walk_loop(err: &ignore::Error) -> bool {
+    match err {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => {
+            is_walk_loop(err)
+        }
+        _ => false,
+    }
+}
+
+fn collect_pattern_matches(
+    preset_name: &str,
+    pattern: &str,
+    base: &Path,
+    exclude: &Option<ExcludeSet>,
+    ignored_patterns: &[String],
+    options: &WalkOptions,
+) -> Result<Vec<PathBuf>> {
+    let patterns = build_preset_patterns(pattern, ignored_patterns);
+    let mut walker =
+        GlobWalkerBuilder::from_patterns(base, &patterns).follow_links(options.follow_links);
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    let walker = walker
+        .build()
+        .with_context(|| format!("invalid glob '{pattern}' in preset '{preset_name}'"))?;
+
+    let mut matches = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A link back to an ancestor: everything below it is already being walked.
+            Err(err) if err.loop_ancestor().is_some() => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let path = entry.into_path();
+        if matches_exclude(exclude, base, &path) {
+            continue;
+        }
+
+        matches.push(path);
+    }
+
+    matches.sort_unstable();
+    Ok(matches)
+}
+
+/// Exclude globs in order, where a leading `!` re-includes what earlier patterns excluded.
+struct ExcludeSet {
+    set: GlobSet,
+    /// Whether each glob in `set`, by index, was written with a leading `!`.
+    negated: Vec<bool>,
+    /// The literal start of each `!` glob, before its first wildcard.
+    reinclude_prefixes: Vec<String>,
+}
+
+impl ExcludeSet {
+    /// Like gitignore, the last pattern that matches decides.
+    fn is_excluded(&self, candidate: &Path) -> bool {
+        self.set
+            .matches(candidate)
+            .into_iter()
+            .max()
+            .is_some_and(|idx| !self.negated[idx])
+    }
+
+    /// Whether a `!` pattern could match something inside `dir` (relative to the base).
+    fn may_reinclude_below(&self, dir: &Path) -> bool {
+        let mut dir: String = dir
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        dir.push('/');
+        self.reinclude_prefixes
+            .iter()
+            .any(|prefix| prefix.starts_with(&dir) || dir.starts_with(prefix.as_str()))
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<Option<ExcludeSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    let mut negated = Vec::with_capacity(patterns.len());
+    let mut reinclude_prefixes = Vec::new();
+    for pattern in patterns {
+        let (glob, negate) = match pattern.strip_prefix('!') {
+            Some(glob) => (glob, true),
+            None => (pattern.as_str(), false),
+        };
+        let globs = exclude_globs(glob);
+        if negate {
+            let literal = globs[1]
+                .split(['*', '?', '[', '{'])
+                .next()
+                .unwrap_or_default();
+            reinclude_prefixes.push(literal.to_string());
+        }
+        for glob in globs {
+            let glob = GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("invalid exclude glob pattern '{pattern}'"))?;
+            builder.add(glob);
+            negated.push(negate);
+        }
+    }
+
+    let set = builder.build()?;
+    Ok(Some(ExcludeSet {
+        set,
+        negated,
+        reinclude_prefixes,
+    }))
+}
+
+/// Translate an exclude pattern into globs over base-relative paths with the same meaning
+/// include patterns have: `*` stops at `/`, a pattern without a `/` matches at any depth,
+/// one with a `/` (or a leading `/`) is anchored to the base, and a pattern naming a
+/// directory also covers everything below it.
+fn exclude_globs(pattern: &str) -> [String; 2] {
+    let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
+    let pattern = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if !pattern.contains('/') => format!("**/{pattern}"),
+        None => pattern.to_string(),
+    };
+    [format!("{pattern}/**"), pattern]
+}
+
+fn matches_exclude(set: &Option<ExcludeSet>, base: &Path, path: &Path) -> bool {
+    match set {
+        Some(set) => {
+            let candidate = path.strip_prefix(base).unwrap_or(path);
+            set.is_excluded(candidate)
+        }
+        None => false,
+    }
+}
+
+/// Whether the walk should drop `path`: an excluded file, or an excluded directory that
+/// no `!` pattern could reach into.
+fn prunes_entry(set: &Option<ExcludeSet>, base: &Path, path: &Path, is_dir: bool) -> bool {
+    let Some(exclude) = set else {
+        return false;
+    };
+    let candidate = path.strip_prefix(base).unwrap_or(path);
+    exclude.is_excluded(candidate) && !(is_dir && exclude.may_reinclude_below(candidate))
+}
+
+/// Order applied to the gathered files by `--sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortMode {
+    /// Full path, byte order (the order directory walks already use).
+    Path,
+    /// File size, smallest first.
+    Size,
+    /// Modification time, oldest first.
+    Mtime,
+    /// File name, case-insensitive, ignoring the directory.
+    Name,
+}
+
+/// Stably sort `files` by `mode`, keeping a leading README pinned there when `pin_readme`.
+///
+/// Files whose metadata can't be read sort first for `size` and `mtime`.
+pub fn sort_files(files: &mut Vec<PathBuf>, mode: SortMode, reverse: bool, pin_readme: bool) {
+    let readme = match files.first() {
+        Some(first) if pin_readme && is_readme(first) => Some(files.remove(0)),
+        _ => None,
+    };
+
+    match mode {
+        SortMode::Path => files.sort(),
+        SortMode::Size => {
+            files.sort_by_cached_key(|path| fs::metadata(path).map(|meta| meta.len()).ok())
+        }
+        SortMode::Mtime => files
+            .sort_by_cached_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok()),
+        SortMode::Name => files.sort_by_cached_key(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+        }),
+    }
+    if reverse {
+        files.reverse();
+    }
+
+    if let Some(readme) = readme {
+        files.insert(0, readme);
+    }
+}
+
+/// Move the preferred README (a direct child of `base` first) to the front of `files`.
+pub fn promote_readme(base: &Path, files: &mut Vec<PathBuf>) {
+    if files.len() <= 1 {
+        return;
+    }
+
+    if let Some(idx) = find_preferred_readme(base, files)
+        && idx != 0
+    {
+        let readme = files.remove(idx);
+        files.insert(0, readme);
+    }
+}
+
+fn find_preferred_readme(base: &Path, files: &[PathBuf]) -> Option<usize> {
+    let mut fallback = None;
+    for (idx, path) in files.iter().enumerate() {
+        if !is_readme(path) {
+            continue;
+        }
+
+        if is_direct_child(base, path) {
+            return Some(idx);
+        }
+
+        if fallback.is_none() {
+            fallback = Some(idx);
+        }
+    }
+
+    fallback
+}
+
+fn is_direct_child(base: &Path, path: &Path) -> bool {
+    match path.strip_prefix(base) {
+        Ok(relative) => relative.components().count() == 1,
+        Err(_) => false,
+    }
+}
+
+fn is_readme(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_ascii_lowercase().starts_with("readme"))
+        .unwrap_or(false)
+}
+
+fn is_ignored_dir(entry: &DirEntry, ignored_dirs: &[String]) -> bool {
+    if !entry
+        .file_type()
+        .is_some_and(|file_type| file_type.is_dir())
+    {
+        return false;
+    }
+
+    let name = entry.file_name();
+    let name = match name.to_str() {
+        Some(name) => name,
+        None => return false,
+    };
+
+    ignored_dirs.iter().any(|dir| dir == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigFile;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn promotes_readme_in_directory() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        write_file(path.join("README.md"), "# hi");
+        write_file(path.join("b.txt"), "b");
+        write_file(path.join("a.txt"), "a");
+
+        let files =
+            collect_from_path(path, &WalkOptions::default(), &mut Warnings::default()).unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|p| p.file_name().unwrap().to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["README.md", "a.txt", "b.txt"]
+        );
+    }
+
+    #[test]
+    fn path_walk_honors_nested_gitignore_and_info_exclude() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        fs::create_dir_all(path.join(".git/info")).unwrap();
+        write_file(path.join(".git/info/exclude"), "secret.txt");
+        write_file(path.join(".gitignore"), "*.log");
+        write_file(path.join("sub/.gitignore"), "!keep.log");
+        write_file(path.join("app.log"), "ignored");
+        write_file(path.join("secret.txt"), "ignored");
+        write_file(path.join("sub/keep.log"), "re-included");
+        write_file(path.join("sub/drop.log"), "ignored");
+        write_file(path.join("src/main.rs"), "main");
+
+        let files =
+            collect_from_path(path, &WalkOptions::default(), &mut Warnings::default()).unwrap();
+        let paths = files
+            .iter()
+            .map(|p| p.strip_prefix(path).unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["src/main.rs", "sub/keep.log"]);
+
+        let options = WalkOptions {
+            respect_gitignore: false,
+            include_hidden: true,
+            ..WalkOptions::default()
+        };
+        let all = collect_from_path(path, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(all.len(), 7);
+    }
+
+    #[test]
+    fn readme_promotion_can_be_disabled() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("README.md"), "readme");
+        write_file(base.join("CHANGELOG.md"), "changes");
+
+        let options = WalkOptions {
+            promote_readme: false,
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![base.join("CHANGELOG.md"), base.join("README.md")]
+        );
+
+        let preset: Preset = serde_yaml::from_str("include: ['*.md']").unwrap();
+        let files = collect_from_preset(
+            "docs",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            vec![base.join("CHANGELOG.md"), base.join("README.md")]
+        );
+    }
+
+    #[test]
+    fn path_walk_skips_hidden_entries_unless_included() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        write_file(path.join(".env"), "SECRET=1");
+        write_file(path.join(".github/workflows/ci.yml"), "on: push");
+        write_file(path.join("src/main.rs"), "main");
+        write_file(path.join(".git/HEAD"), "ref");
+
+        let files =
+            collect_from_path(path, &WalkOptions::default(), &mut Warnings::default()).unwrap();
+        assert_eq!(files, vec![path.join("src/main.rs")]);
+
+        let options = WalkOptions {
+            include_hidden: true,
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(path, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                path.join(".env"),
+                path.join(".github/workflows/ci.yml"),
+                path.join("src/main.rs"),
+            ]
+        );
+
+        let github = collect_from_path(
+            &path.join(".github"),
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(github, vec![path.join(".github/workflows/ci.yml")]);
+    }
+
+    #[test]
+    fn collects_files_from_preset() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("README.md"), "root");
+        write_file(base.join("src/lib.rs"), "lib");
+        write_file(base.join("src/main.rs"), "main");
+        write_file(base.join("src/extra.txt"), "extra");
+
+        let config_yaml = r#"
+version: 1
+presets:
+  rust:
+    base: .
+    include:
+      - "src/**/*.rs"
+    exclude:
+      - "src/lib.rs"
+"#;
+        let config_path = base.join(".gather-files.yaml");
+        fs::write(&config_path, config_yaml).unwrap();
+        let config = ConfigFile::load(&config_path).unwrap().unwrap();
+        let preset = config.preset("rust").unwrap();
+        let files = collect_from_preset(
+            "rust",
+            preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|p| p.strip_prefix(base).unwrap().display().to_string())
+                .collect::<Vec<_>>(),
+            vec!["src/main.rs"]
+        );
+
+        let verbose = WalkOptions {
+            verbose: true,
+            ..WalkOptions::default()
+        };
+        let mut warnings = Warnings::default();
+        collect_from_preset(
+            "rust",
+            preset,
+            &IndexMap::new(),
+            base,
+            &verbose,
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings.take_notes(),
+            [
+                format!("preset 'rust' base {}", base.join(".").display()),
+                "  include 'src/**/*.rs' matched 1 files".to_string()
+            ]
+        );
+        assert!(warnings.messages().is_empty());
+    }
+
+    #[test]
+    fn render_includes_headers() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("README.md");
+        write_file(file.clone(), "Hello world\n");
+
+        let output = render_files(
+            &[file],
+            base,
+            &RenderOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert!(output.0.contains("# README.md"));
+        assert!(output.0.contains("Hello world"));
+        assert_eq!(output.1, output.0.chars().count());
+    }
+
+    #[test]
+    fn preset_skips_ignored_directories() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("README.md"), "root");
+        write_file(base.join("src/main.rs"), "main");
+        write_file(base.join("target/ignored.rs"), "ignore");
+        write_file(base.join("node_modules/pkg/index.js"), "ignore");
+
+        let config_yaml = r#"
+version: 1
+presets:
+  everything:
+    base: .
+    include:
+      - "**/*"
+"#;
+        let config_path = base.join(".gather-files.yaml");
+        fs::write(&config_path, config_yaml).unwrap();
+        let config = ConfigFile::load(&config_path).unwrap().unwrap();
+        let preset = config.preset("everything").unwrap();
+        let files = collect_from_preset(
+            "everything",
+            preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        let paths = files
+            .iter()
+            .map(|p| p.strip_prefix(base).unwrap().display().to_string())
+            .collect::<Vec<_>>();
+
+        assert!(paths.contains(&"README.md".to_string()));
+        assert!(paths.contains(&"src/main.rs".to_string()));
+        assert!(!paths.iter().any(|path| path.starts_with("target/")));
+        assert!(!paths.iter().any(|path| path.starts_with("node_modules/")));
+
+        let options = WalkOptions {
+            ignored_dirs: vec!["src".to_string()],
+            ..WalkOptions::default()
+        };
+        let files = collect_from_preset(
+            "everything",
+            preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert!(files.contains(&base.join("target/ignored.rs")));
+        assert!(!files.contains(&base.join("src/main.rs")));
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert!(!files.contains(&base.join("src/main.rs")));
+    }
+
+    #[test]
+    fn gatherignore_applies_to_path_walks_and_presets() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("src/main.rs"), "main");
+        write_file(base.join("fixtures/huge.json"), "{}");
+        write_file(base.join("src/generated.rs"), "generated");
+        write_file(base.join("docs/notes.md"), "notes");
+        write_file(
+            base.join(GATHERIGNORE_FILE_NAME),
+            "fixtures/\n*generated*\n",
+        );
+
+        let options = WalkOptions {
+            gatherignore: load_gatherignore(base).unwrap(),
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert!(files.contains(&base.join("src/main.rs")));
+        assert!(!files.contains(&base.join("fixtures/huge.json")));
+        assert!(!files.contains(&base.join("src/generated.rs")));
+
+        let preset: Preset =
+            serde_yaml::from_str("include: ['**/*.rs', '**/*.md']\nexclude: ['docs/**']").unwrap();
+        let strict = WalkOptions {
+            strict: true,
+            ..options.clone()
+        };
+        let err = collect_from_preset(
+            "code",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &strict,
+            &mut Warnings::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("'**/*.md'"));
+
+        let preset: Preset = serde_yaml::from_str("include: ['**/*.rs', '**/*.json']").unwrap();
+        let mut warnings = Warnings::default();
+        let files = collect_from_preset(
+            "code",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(files, vec![base.join("src/main.rs")]);
+        assert_eq!(warnings.messages().len(), 1);
+        assert!(warnings.messages()[0].contains("'**/*.json'"));
+
+        let preset: Preset = serde_yaml::from_str("include: ['**/*.json']").unwrap();
+        let err = collect_from_preset(
+            "code",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("no files matched any pattern"));
+
+        let preset: Preset = serde_yaml::from_str("include: ['**/*.rs']").unwrap();
+        let files = collect_from_preset(
+            "code",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![base.join("src/main.rs")]);
+
+        assert!(load_gatherignore(&base.join("src")).unwrap().is_none());
+    }
+
+    #[test]
+    fn group_preset_unions_members_in_order() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("README.md"), "readme");
+        write_file(base.join("src/lib.rs"), "lib");
+        write_file(base.join("docs/guide.md"), "guide");
+        write_file(base.join("Cargo.toml"), "[package]");
+
+        let presets: IndexMap<String, Preset> = serde_yaml::from_str(
+            "src: { include: ['src/**/*.rs'] }\ndocs: { include: ['**/*.md'] }\nconfig: { include: ['Cargo.toml', 'README.md'] }\ncode: { presets: [src, config] }\nall: { presets: [docs, code] }\n",
+        )
+        .unwrap();
+        let files = collect_from_preset(
+            "all",
+            &presets["all"],
+            &presets,
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            vec![
+                base.join("README.md"),
+                base.join("docs/guide.md"),
+                base.join("src/lib.rs"),
+                base.join("Cargo.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn preset_from_file_keeps_listed_order_before_includes() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("app/README.md"), "readme");
+        write_file(base.join("app/src/z.rs"), "z");
+        write_file(base.join("app/src/a.rs"), "a");
+        write_file(base.join("app/docs/guide.md"), "guide");
+        write_file(
+            base.join("lists/app.txt"),
+            "# reading order\nsrc/z.rs\n\nsrc/a.rs\n",
+        );
+
+        let preset: Preset = serde_yaml::from_str(
+            "base: app\nfrom_file: lists/app.txt\ninclude: ['**/*.md', 'src/*.rs']",
+        )
+        .unwrap();
+        let files = collect_from_preset(
+            "app",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            vec![
+                base.join("app/src/z.rs"),
+                base.join("app/src/a.rs"),
+                base.join("app/README.md"),
+                base.join("app/docs/guide.md"),
+            ]
+        );
+
+        write_file(base.join("lists/app.txt"), "src/z.rs\nsrc/gone.rs\n");
+        let preset: Preset = serde_yaml::from_str("base: app\nfrom_file: lists/app.txt").unwrap();
+        let err = collect_from_preset(
+            "app",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("src/gone.rs"), "{err}");
+    }
+
+    #[test]
+    fn negated_excludes_reinclude_in_order() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        for file in [
+            "src/lib.rs",
+            "docs/README.md",
+            "docs/guide.md",
+            "docs/old/README.md",
+        ] {
+            write_file(base.join(file), file);
+        }
+
+        let preset: Preset = serde_yaml::from_str(
+            "include: ['**/*']\nexclude: ['docs/**', '!**/README.md', 'docs/old/**']",
+        )
+        .unwrap();
+        let files = collect_from_preset(
+            "code",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            vec![base.join("docs/README.md"), base.join("src/lib.rs")]
+        );
+
+        let options = WalkOptions {
+            exclude: vec!["docs/**".to_string(), "!docs/guide.md".to_string()],
+            ..WalkOptions::default()
+        };
+        // docs/old itself is excluded, so the walk never reaches its README.
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![base.join("docs/guide.md"), base.join("src/lib.rs")]
+        );
+
+        // An excluded directory is still walked when a later `!` pattern reaches inside it.
+        let walk = |cli_exclude: &[&str]| {
+            let options = WalkOptions {
+                cli_exclude: cli_exclude.iter().map(|p| p.to_string()).collect(),
+                ..WalkOptions::default()
+            };
+            collect_from_path(base, &options, &mut Warnings::default()).unwrap()
+        };
+        assert_eq!(
+            walk(&["docs", "!docs/README.md"]),
+            vec![base.join("docs/README.md"), base.join("src/lib.rs")]
+        );
+        assert_eq!(
+            walk(&["docs", "src", "!**/README.md"]),
+            vec![base.join("docs/README.md"), base.join("docs/old/README.md")]
+        );
+        assert_eq!(
+            walk(&["src", "!docs/README.md"]),
+            vec![
+                base.join("docs/README.md"),
+                base.join("docs/guide.md"),
+                base.join("docs/old/README.md")
+            ]
+        );
+    }
+
+    #[test]
+    fn excludes_match_base_relative_paths_like_includes() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        for file in [
+            "crates/core/src/lib.rs",
+            "crates/core/src/lib.test.rs",
+            "crates/core/src/deep/parse.rs",
+            "crates/core/src/deep/parse.test.rs",
+            "crates/core/src/deep/parse.snap",
+            "crates/core/fixtures/data.rs",
+        ] {
+            write_file(base.join(file), file);
+        }
+
+        let preset: Preset = serde_yaml::from_str(
+            "base: crates/core\ninclude: ['**/*']\nexclude: ['**/*.test.rs', '*.snap', 'src/*.rs', 'fixtures']",
+        )
+        .unwrap();
+        let files = collect_from_preset(
+            "core",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &WalkOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        // `src/*.rs` is anchored and `*` stops at `/`, so only the top-level file goes.
+        let expected = vec![base.join("crates/core/src/deep/parse.rs")];
+        assert_eq!(files, expected);
+
+        let options = WalkOptions {
+            exclude: preset.exclude.clone(),
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(
+            &base.join("crates/core"),
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn max_depth_limits_path_walks_and_preset_globs() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        for file in ["a.rs", "sub/b.rs", "sub/deep/c.rs"] {
+            write_file(base.join(file), file);
+        }
+
+        let options = WalkOptions {
+            max_depth: Some(1),
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(files, vec![base.join("a.rs")]);
+
+        let options = WalkOptions {
+            max_depth: Some(2),
+            ..WalkOptions::default()
+        };
+        let preset: Preset = serde_yaml::from_str("include: ['**/*.rs']").unwrap();
+        let files = collect_from_preset(
+            "rust",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![base.join("a.rs"), base.join("sub/b.rs")]);
     }
 
     #[test]
-    fn render_includes_headers() {
+    fn config_excludes_apply_to_presets_and_path_walks() {
         let dir = tempdir().unwrap();
         let base = dir.path();
-        let file = base.join("README.md");
-        write_file(file.clone(), "Hello world\n");
+        write_file(base.join("src/main.rs"), "main");
+        write_file(base.join("src/generated/api.rs"), "generated");
+        write_file(base.join("Cargo.lock"), "lock");
+        write_file(base.join("web/package-lock.json"), "lock");
 
-        let output = render_files(&[file], base).unwrap();
-        assert!(output.0.contains("# README.md"));
-        assert!(output.0.contains("Hello world"));
-        assert_eq!(output.1, output.0.chars().count());
+        let options = WalkOptions {
+            exclude: vec!["**/generated/**".to_string(), "**/*.lock".to_string()],
+            ..WalkOptions::default()
+        };
+        let files = collect_from_path(base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![base.join("src/main.rs"), base.join("web/package-lock.json")]
+        );
+
+        let preset: Preset =
+            serde_yaml::from_str("include: ['**/*']\nexclude: ['web/**']").unwrap();
+        let files = collect_from_preset(
+            "all",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![base.join("src/main.rs")]);
     }
 
     #[test]
-    fn preset_skips_ignored_directories() {
+    fn render_trims_imports_when_enabled() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("lib.rs");
+        write_file(file.clone(), "use std::fs;\nuse std::io;\n\npub fn a() {}");
+
+        let options = RenderOptions {
+            trim_imports: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(
+            output.contains("// [gf: skipped 2 imports — import block collapsed]\n\npub fn a() {}")
+        );
+        assert!(!output.contains("use std::fs;"));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_head_keeps_original_line_numbers_and_counts_truncated_text() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("log.txt");
+        write_file(file.clone(), "a\nb\nc\nd");
+
+        let options = RenderOptions {
+            line_numbers: true,
+            line_limit: Some(LineLimit::Tail(1)),
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            output,
+            "-------\n# log.txt\n\n[gf: skipped 3 more lines — truncated by --tail 1]\n4 | d\n\n"
+        );
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_markdown_fences_with_language_hint() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("main.rs");
+        write_file(file.clone(), "let s = \"```\";");
+
+        let options = RenderOptions {
+            format: OutputFormat::Markdown,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.starts_with("## main.rs\n\n````rust\nlet s = \"```\";\n````\n"));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn markdown_language_uses_file_names_then_extensions() {
+        assert_eq!(
+            markdown_language(Path::new("docker/Dockerfile")),
+            "dockerfile"
+        );
+        assert_eq!(markdown_language(Path::new("Makefile")), "makefile");
+        assert_eq!(markdown_language(Path::new("lib/App.KT")), "kotlin");
+        assert_eq!(markdown_language(Path::new("include/util.hpp")), "cpp");
+        assert_eq!(markdown_language(Path::new("notes.txt")), "");
+        assert_eq!(markdown_language(Path::new("LICENSE")), "");
+    }
+
+    #[test]
+    fn render_numbers_lines_inside_markdown_fences() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("main.rs");
+        write_file(file.clone(), "fn main() {\n}");
+
+        let options = RenderOptions {
+            format: OutputFormat::Markdown,
+            line_numbers: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.contains("```rust\n1 | fn main() {\n2 | }\n```\n"));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_tree_groups_directories_first() {
+        let root = Path::new("/repo");
+        let files: Vec<PathBuf> = [
+            "README.md",
+            "src/main.rs",
+            "src/gather/walk.rs",
+            "Cargo.toml",
+        ]
+        .iter()
+        .map(|name| root.join(name))
+        .collect();
+
+        assert_eq!(
+            render_tree(&files, root),
+            ".\n\
+             ├── src\n\
+             │   ├── gather\n\
+             │   │   └── walk.rs\n\
+             │   └── main.rs\n\
+             ├── Cargo.toml\n\
+             └── README.md\n"
+        );
+    }
+
+    #[test]
+    fn render_prepends_tree_and_counts_it() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("src/lib.rs");
+        write_file(file.clone(), "pub fn a() {}\n");
+
+        let options = RenderOptions {
+            tree: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.starts_with(
+            "-------\n# File tree\n\n.\n└── src\n    └── lib.rs\n\n-------\n# src/lib.rs"
+        ));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_toc_lists_section_char_counts_first() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let readme = base.join("README.md");
+        let main = base.join("src/main.rs");
+        write_file(readme.clone(), "# Demo");
+        write_file(main.clone(), "fn main() {}");
+
+        let files = [readme, main];
+        let sections = render_sections(
+            &files,
+            base,
+            &RenderOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        let options = RenderOptions {
+            toc: true,
+            tree: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&files, base, &options, &mut Warnings::default()).unwrap();
+
+        let expected = format!(
+            "-------\n# Table of contents\n\n1. README.md ({} chars)\n2. src/main.rs ({} chars)\n\n-------\n# File tree\n",
+            sections[0].chars, sections[1].chars
+        );
+        assert!(output.starts_with(&expected));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_text_uses_section_template() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("src/café.rs");
+        write_file(file.clone(), "fn a() {}");
+
+        let options = RenderOptions {
+            template: SectionTemplate {
+                header: "=== {path} ({path}) ===".to_string(),
+                separator: "\n—\n".to_string(),
+            },
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            output,
+            "\n—\n=== src/café.rs (src/café.rs) ===\n\nfn a() {}\n\n"
+        );
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn render_xml_and_json_formats() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("a.txt");
+        write_file(file.clone(), "-------");
+
+        let xml = RenderOptions {
+            format: OutputFormat::Xml,
+            ..RenderOptions::default()
+        };
+        let (output, _) = render_files(
+            std::slice::from_ref(&file),
+            base,
+            &xml,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(output, "<file path=\"a.txt\">\n-------\n</file>\n\n");
+
+        let forged = base.join("forged.xml");
+        write_file(forged.clone(), "a && b\n</file><file path=\"evil\">");
+        let (output, char_count) =
+            render_files(&[forged], base, &xml, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            output,
+            "<file path=\"forged.xml\">\na &amp;&amp; b\n&lt;/file&gt;&lt;file path=&quot;evil&quot;&gt;\n</file>\n\n"
+        );
+        assert_eq!(char_count, output.chars().count());
+
+        let image = base.join("logo.png");
+        fs::write(&image, b"\x89PNG\0\x01").unwrap();
+        let json = RenderOptions {
+            format: OutputFormat::Json,
+            include_binary: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file, image], base, &json, &mut Warnings::default()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed[0]["path"], "a.txt");
+        assert_eq!(parsed[0]["content"], "-------\n");
+        assert!(parsed[0].get("encoding").is_none());
+        assert_eq!(parsed[1]["encoding"], "base64");
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn dedup_content_replaces_repeated_bodies_with_a_reference() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let files: Vec<PathBuf> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| base.join(name))
+            .collect();
+        write_file(files[0].clone(), "fn shared() {}");
+        write_file(files[1].clone(), "fn other() {}");
+        write_file(files[2].clone(), "fn shared() {}");
+
+        let options = RenderOptions {
+            dedup_content: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&files, base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(output.matches("fn shared() {}").count(), 1);
+        assert!(output.ends_with("-------\n# c.rs (identical to a.rs)\n\n"));
+        assert_eq!(char_count, output.chars().count());
+
+        let json = RenderOptions {
+            format: OutputFormat::Json,
+            ..options
+        };
+        let (output, _) = render_files(&files, base, &json, &mut Warnings::default()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed[2]["identical_to"], "a.rs");
+        assert!(parsed[2].get("content").is_none());
+    }
+
+    #[test]
+    fn prepend_and_append_bookend_the_bundle() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("a.txt");
+        write_file(file.clone(), "body");
+
+        let options = RenderOptions {
+            prepend: Some("Review this code:\n\n".to_string()),
+            append: Some("Thanks — be brief.\n".to_string()),
+            ..RenderOptions::default()
+        };
+        let (output, char_count) = render_files(
+            std::slice::from_ref(&file),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "Review this code:\n\n-------\n# a.txt\n\nbody\n\nThanks — be brief.\n"
+        );
+        assert_eq!(char_count, output.chars().count());
+
+        let json = RenderOptions {
+            format: OutputFormat::Json,
+            ..options
+        };
+        let mut warnings = Warnings::default();
+        let (output, _) = render_files(&[file], base, &json, &mut warnings).unwrap();
+        assert!(output.starts_with('['));
+        assert_eq!(
+            warnings.messages(),
+            [
+                "--prepend is ignored for JSON output",
+                "--append-file is ignored for JSON output"
+            ]
+        );
+    }
+
+    #[test]
+    fn render_counts_normalized_line_endings() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("win.txt");
+        fs::write(&file, "\u{feff}a\r\nb\r\n").unwrap();
+
+        let options = RenderOptions {
+            line_endings: LineEndings::Lf,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.contains("a\nb\n"));
+        assert!(!output.contains('\r'));
+        assert!(!output.contains('\u{feff}'));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[test]
+    fn sort_files_orders_by_mode_and_pins_readme() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("README.md"), "readme");
+        write_file(base.join("b/zeta.rs"), "z");
+        write_file(base.join("a/Beta.rs"), "bbbbbbbb");
+        write_file(base.join("c/alpha.rs"), "aaaa");
+
+        let files = vec![
+            base.join("README.md"),
+            base.join("b/zeta.rs"),
+            base.join("a/Beta.rs"),
+            base.join("c/alpha.rs"),
+        ];
+        let names = |files: &[PathBuf]| {
+            files
+                .iter()
+                .map(|path| display_path(path, base))
+                .collect::<Vec<_>>()
+        };
+
+        let mut sorted = files.clone();
+        sort_files(&mut sorted, SortMode::Path, false, true);
+        assert_eq!(
+            names(&sorted),
+            ["README.md", "a/Beta.rs", "b/zeta.rs", "c/alpha.rs"]
+        );
+
+        sort_files(&mut sorted, SortMode::Name, false, true);
+        assert_eq!(
+            names(&sorted),
+            ["README.md", "c/alpha.rs", "a/Beta.rs", "b/zeta.rs"]
+        );
+
+        sort_files(&mut sorted, SortMode::Size, true, true);
+        assert_eq!(
+            names(&sorted),
+            ["README.md", "a/Beta.rs", "c/alpha.rs", "b/zeta.rs"]
+        );
+
+        sort_files(&mut sorted, SortMode::Size, false, false);
+        assert_eq!(
+            names(&sorted),
+            ["b/zeta.rs", "c/alpha.rs", "README.md", "a/Beta.rs"]
+        );
+    }
+
+    #[test]
+    fn filter_by_content_keeps_matching_text_files() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("a.rs"), "fn parse_config() {}");
+        write_file(base.join("b.rs"), "fn render() {}");
+        fs::write(base.join("c.bin"), b"parse_config\0").unwrap();
+
+        let files = vec![
+            base.join("a.rs"),
+            base.join("b.rs"),
+            base.join("c.bin"),
+            base.join("missing.rs"),
+        ];
+        let mut warnings = Warnings::default();
+        let pattern = Regex::new(r"parse_\w+").unwrap();
+        let (kept, dropped) = filter_by_content(files, &pattern, base, &mut warnings);
+        assert_eq!(kept, vec![base.join("a.rs")]);
+        assert_eq!(dropped, 3);
+        assert_eq!(warnings.messages().len(), 1);
+        assert!(warnings.messages()[0].starts_with("skipped missing.rs for --grep"));
+    }
+
+    #[test]
+    fn filter_empty_drops_blank_files() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        fs::write(base.join("empty.rs"), "").unwrap();
+        fs::write(base.join("blank.rs"), " \n\t\r\n").unwrap();
+        fs::write(base.join("code.rs"), "\nfn a() {}\n").unwrap();
+
+        let files = vec![
+            base.join("empty.rs"),
+            base.join("blank.rs"),
+            base.join("code.rs"),
+        ];
+        let (kept, dropped) = filter_empty(files);
+        assert_eq!(kept, vec![base.join("code.rs")]);
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn dedup_files_collapses_equivalent_paths() {
         let dir = tempdir().unwrap();
         let base = dir.path();
-        write_file(base.join("README.md"), "root");
         write_file(base.join("src/main.rs"), "main");
-        write_file(base.join("target/ignored.rs"), "ignore");
-        write_file(base.join("node_modules/pkg/index.js"), "ignore");
+        write_file(base.join("README.md"), "readme");
 
-        let config_yaml = r#"
-version: 1
-presets:
-  everything:
-    base: .
-    include:
-      - "**/*"
-"#;
-        let config_path = base.join(".gather-files.yaml");
-        fs::write(&config_path, config_yaml).unwrap();
-        let config = ConfigFile::load(&config_path).unwrap().unwrap();
-        let preset = config.preset("everything").unwrap();
-        let files = collect_from_preset("everything", preset, base).unwrap();
-        let paths = files
+        let files = vec![
+            base.join("README.md"),
+            base.join("src/main.rs"),
+            base.join("src/../README.md"),
+            base.join("src/./main.rs"),
+        ];
+        assert_eq!(
+            dedup_files(files),
+            vec![base.join("README.md"), base.join("src/main.rs")]
+        );
+    }
+
+    #[test]
+    fn filter_tests_uses_path_heuristics() {
+        let root = Path::new("/repo");
+        let files = vec![
+            root.join("src/lib.rs"),
+            root.join("src/lib_test.rs"),
+            root.join("tests/cli.rs"),
+            root.join("web/app.test.ts"),
+            root.join("py/test_app.py"),
+        ];
+        let patterns: Vec<String> = DEFAULT_TEST_PATTERNS
             .iter()
-            .map(|p| p.strip_prefix(base).unwrap().display().to_string())
-            .collect::<Vec<_>>();
+            .map(|p| p.to_string())
+            .collect();
 
-        assert!(paths.contains(&"README.md".to_string()));
-        assert!(paths.contains(&"src/main.rs".to_string()));
-        assert!(!paths.iter().any(|path| path.starts_with("target/")));
-        assert!(!paths.iter().any(|path| path.starts_with("node_modules/")));
+        let code = filter_tests(files.clone(), root, &patterns, TestFilter::Exclude).unwrap();
+        assert_eq!(code, vec![root.join("src/lib.rs")]);
+
+        let tests = filter_tests(files, root, &patterns, TestFilter::Only).unwrap();
+        assert_eq!(tests.len(), 4);
+    }
+
+    #[test]
+    fn wrap_all_uses_fence_longer_than_contents() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("notes.md");
+        write_file(file.clone(), "````\ncode\n````");
+
+        let options = RenderOptions {
+            wrap_all: true,
+            ..RenderOptions::default()
+        };
+        let (output, char_count) = render_files(
+            std::slice::from_ref(&file),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert!(output.starts_with("`````\n-------\n# notes.md\n"));
+        assert!(output.ends_with("\n`````\n"));
+        assert_eq!(char_count, output.chars().count());
+
+        let json = RenderOptions {
+            format: OutputFormat::Json,
+            ..options
+        };
+        let mut warnings = Warnings::default();
+        let (output, _) = render_files(&[file], base, &json, &mut warnings).unwrap();
+        assert!(output.starts_with('['));
+        assert_eq!(
+            warnings.messages(),
+            ["--wrap-all is ignored for JSON output"]
+        );
+    }
+
+    #[test]
+    fn render_appends_virtual_files() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let file = base.join("main.rs");
+        write_file(file.clone(), "fn main() {}");
+
+        let options = RenderOptions {
+            virtual_files: vec![VirtualFile {
+                name: "build-errors.txt".to_string(),
+                contents: "error[E0425]".to_string(),
+            }],
+            ..RenderOptions::default()
+        };
+        let (output, char_count) =
+            render_files(&[file], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.ends_with("-------\n# build-errors.txt\n\nerror[E0425]\n\n"));
+        assert!(output.starts_with("-------\n# main.rs\n"));
+        assert_eq!(char_count, output.chars().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_links_walks_symlinked_dirs_and_skips_cycles() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        write_file(base.join("vendor/lib/code.rs"), "code");
+        fs::create_dir_all(base.join("app")).unwrap();
+        std::os::unix::fs::symlink(base.join("vendor/lib"), base.join("app/lib")).unwrap();
+        std::os::unix::fs::symlink(base.join("app"), base.join("app/again")).unwrap();
+        let app = base.join("app");
+
+        let options = WalkOptions::default();
+        let preset: Preset = serde_yaml::from_str("base: app\ninclude: ['**/*.rs']").unwrap();
+        assert!(
+            collect_from_path(&app, &options, &mut Warnings::default())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            collect_from_preset(
+                "app",
+                &preset,
+                &IndexMap::new(),
+                base,
+                &options,
+                &mut Warnings::default()
+            )
+            .is_err()
+        );
+
+        let options = WalkOptions {
+            follow_links: true,
+            ..WalkOptions::default()
+        };
+        let mut warnings = Warnings::default();
+        let files = collect_from_path(&app, &options, &mut warnings).unwrap();
+        assert_eq!(files, vec![app.join("lib/code.rs")]);
+        assert!(warnings.messages().is_empty());
+        let files = collect_from_preset(
+            "app",
+            &preset,
+            &IndexMap::new(),
+            base,
+            &options,
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![app.join("lib/code.rs")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn render_applies_content_filters_by_extension() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let notebook = base.join("analysis.ipynb");
+        let plain = base.join("notes.txt");
+        write_file(notebook.clone(), "cells");
+        write_file(plain.clone(), "cells");
+
+        let options = RenderOptions {
+            content_filters: IndexMap::from([("ipynb".to_string(), "tr a-z A-Z".to_string())]),
+            ..RenderOptions::default()
+        };
+        let (output, _) =
+            render_files(&[notebook, plain], base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.contains("# analysis.ipynb\n\nCELLS\n"));
+        assert!(output.contains("# notes.txt\n\ncells\n"));
+    }
+
+    #[test]
+    fn parallel_reads_keep_file_and_warning_order() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let files: Vec<PathBuf> = (0..PARALLEL_READ_THRESHOLD * 4)
+            .map(|idx| base.join(format!("{idx:03}.txt")))
+            .collect();
+        for (idx, file) in files.iter().enumerate() {
+            if idx % 10 != 7 {
+                write_file(file.clone(), &format!("file {idx}"));
+            }
+        }
+
+        let options = RenderOptions {
+            no_annotations: true,
+            ..RenderOptions::default()
+        };
+        let mut warnings = Warnings::default();
+        let (output, char_count) = render_files(&files, base, &options, &mut warnings).unwrap();
+        let mut expected = String::new();
+        for (idx, _) in files.iter().enumerate().filter(|(idx, _)| idx % 10 != 7) {
+            expected.push_str(&format!("-------\n# {idx:03}.txt\n\nfile {idx}\n\n"));
+        }
+        assert_eq!(output, expected);
+        assert_eq!(char_count, expected.chars().count());
+        let skipped: Vec<&str> = warnings
+            .messages()
+            .iter()
+            .map(|message| &message["skipped ".len().."skipped 007.txt".len()])
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                "007.txt", "017.txt", "027.txt", "037.txt", "047.txt", "057.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn render_warns_about_lossy_and_unreadable_files() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let latin1 = base.join("latin1.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        let missing = base.join("missing.txt");
+
+        let mut warnings = Warnings::default();
+        let (output, _) = render_files(
+            &[latin1, missing],
+            base,
+            &RenderOptions::default(),
+            &mut warnings,
+        )
+        .unwrap();
+        assert!(output.contains("# latin1.txt\n\ncaf\u{FFFD}\n"));
+        assert!(output.contains("\n[gf: skipped missing.txt — "));
+        assert_eq!(warnings.messages().len(), 2);
+        assert!(warnings.messages()[0].starts_with("latin1.txt: not valid UTF-8"));
+        assert!(warnings.messages()[1].starts_with("skipped missing.txt"));
+    }
+
+    #[test]
+    fn render_skips_binary_files_unless_included() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let image = base.join("logo.png");
+        fs::write(&image, b"\x89PNG\0\x01").unwrap();
+        let files = [image];
+
+        let mut warnings = Warnings::default();
+        let (output, char_count) =
+            render_files(&files, base, &RenderOptions::default(), &mut warnings).unwrap();
+        let marker = "[gf: skipped logo.png — binary file]\n\n";
+        assert_eq!(
+            (output.as_str(), char_count),
+            (marker, marker.chars().count())
+        );
+        assert_eq!(warnings.messages(), ["skipped logo.png: binary file"]);
+
+        let options = RenderOptions {
+            no_annotations: true,
+            ..RenderOptions::default()
+        };
+        let (output, _) = render_files(&files, base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(output, "");
+
+        let options = RenderOptions {
+            include_binary: true,
+            ..RenderOptions::default()
+        };
+        let (output, _) = render_files(&files, base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.contains("# logo.png\n\niVBORwAB\n"));
+    }
+
+    #[test]
+    fn render_bundle_reports_section_sizes_and_skips() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let text = base.join("a.txt");
+        let image = base.join("logo.png");
+        fs::write(&text, "abc\n").unwrap();
+        fs::write(&image, b"\x89PNG\0\x01").unwrap();
+        let files = [text, image];
+
+        let rendered = render_bundle(
+            &files,
+            base,
+            &RenderOptions::default(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        let sizes: Vec<(&str, bool)> = rendered
+            .sections
+            .iter()
+            .map(|section| (section.display.as_str(), section.skipped))
+            .collect();
+        assert_eq!(sizes, [("a.txt", false), ("logo.png", true)]);
+        assert_eq!(
+            rendered.sections[0].chars,
+            "-------\n# a.txt\n\nabc\n\n".len()
+        );
+        assert_eq!(count_rendered(&rendered.sections), 1);
+
+        let options = RenderOptions {
+            format: OutputFormat::Json,
+            ..RenderOptions::default()
+        };
+        let rendered = render_bundle(&files, base, &options, &mut Warnings::default()).unwrap();
+        assert_eq!(
+            rendered.sections,
+            [SectionSize {
+                display: "a.txt".to_string(),
+                chars: 4,
+                skipped: false
+            }]
+        );
+    }
+
+    #[test]
+    fn size_limits_skip_large_files_and_stop_at_total() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let files: Vec<PathBuf> = ["a.txt", "big.txt", "b.txt", "c.txt", "d.txt"]
+            .iter()
+            .map(|name| base.join(name))
+            .collect();
+        for (path, size) in files.iter().zip([10, 500, 10, 10, 10]) {
+            fs::write(path, "x".repeat(size)).unwrap();
+        }
+
+        let limits = SizeLimits {
+            max_file: Some(100),
+            max_total: Some(25),
+        };
+        let mut warnings = Warnings::default();
+        let (kept, trim) = apply_size_limits(files.clone(), base, limits, &mut warnings);
+        assert_eq!(kept, vec![files[0].clone(), files[2].clone()]);
+        assert_eq!((trim.oversized, trim.over_total), (1, 2));
+        assert_eq!(warnings.messages().len(), 2);
+
+        let options = RenderOptions {
+            omissions: trim.omissions,
+            ..RenderOptions::default()
+        };
+        let (output, _) = render_files(&kept, base, &options, &mut Warnings::default()).unwrap();
+        assert!(output.ends_with(
+            "[gf: skipped big.txt — 500 bytes exceeds --max-file-size (100 bytes)]\n\n\
+             [gf: skipped 2 files starting at c.txt — --max-total-size (25 bytes) reached]\n\n"
+        ));
+        let options = RenderOptions {
+            no_annotations: true,
+            ..options
+        };
+        let (output, _) = render_files(&kept, base, &options, &mut Warnings::default()).unwrap();
+        assert!(!output.contains("[gf:"));
+    }
+
+    #[test]
+    fn size_limits_skip_files_whose_size_cannot_be_read() {
+        let dir = tempdir().unwrap();
+        let base = dir.path();
+        let kept_file = base.join("a.txt");
+        write_file(kept_file.clone(), "a");
+        let files = vec![base.join("gone.txt"), kept_file.clone()];
+
+        let limits = SizeLimits {
+            max_file: Some(100),
+            max_total: None,
+        };
+        let mut warnings = Warnings::default();
+        let (kept, trim) = apply_size_limits(files, base, limits, &mut warnings);
+        assert_eq!(kept, vec![kept_file]);
+        assert_eq!((trim.oversized, trim.over_total), (0, 0));
+        assert_eq!(trim.omissions.len(), 1);
+        assert_eq!(trim.omissions[0].what, "gone.txt");
+        assert!(warnings.messages()[0].starts_with("skipped gone.txt: "));
     }
 
     fn write_file(path: PathBuf, contents: &str) {
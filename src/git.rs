@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Working-tree status of a file as reported by `git status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Clean,
+    Modified,
+    Staged,
+    Untracked,
+}
+
+impl FileStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            FileStatus::Clean => "clean",
+            FileStatus::Modified => "modified",
+            FileStatus::Staged => "staged",
+            FileStatus::Untracked => "untracked",
+        }
+    }
+}
+
+/// Run `git` in `repo_root` and return its stdout, failing with git's own message.
+fn run_git(repo_root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .output()
+        .context("failed to run git")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args.join(" "), stderr.trim());
+    }
+
+    String::from_utf8(output.stdout).context("git output was not valid UTF-8")
+}
+
+/// Fail clearly when `repo_root` isn't inside a git work tree; `flag` names the option that needs it.
+fn ensure_work_tree(repo_root: &Path, flag: &str) -> Result<()> {
+    run_git(repo_root, &["rev-parse", "--is-inside-work-tree"])
+        .map(drop)
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "{flag} needs a git repository, but {} is not inside one",
+                repo_root.display()
+            )
+        })
+}
+
+/// Files under `repo_root` with unstaged or staged changes, in path order.
+///
+/// Deleted files are skipped since there is nothing left to read.
+pub fn changed_files(repo_root: &Path) -> Result<Vec<PathBuf>> {
+    ensure_work_tree(repo_root, "--changed")?;
+    let mut names = BTreeSet::new();
+    for cached in [false, true] {
+        let mut args = vec!["diff", "--name-only", "-z", "--relative"];
+        if cached {
+            args.push("--cached");
+        }
+        let output = run_git(repo_root, &args)?;
+        names.extend(
+            output
+                .split('\0')
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+        );
+    }
+    Ok(existing_files(repo_root, names))
+}
+
+/// Files under `repo_root` changed on this branch since it diverged from `rev`
+/// (`git diff rev...HEAD`), at their current paths. Deleted files are skipped.
+pub fn files_changed_since(repo_root: &Path, rev: &str) -> Result<Vec<PathBuf>> {
+    ensure_work_tree(repo_root, "--since")?;
+    let commit = format!("{rev}^{{commit}}");
+    if run_git(repo_root, &["rev-parse", "--verify", "--quiet", &commit]).is_err() {
+        anyhow::bail!("--since: git ref '{rev}' does not resolve to a commit");
+    }
+
+    let range = format!("{rev}...HEAD");
+    let output = run_git(
+        repo_root,
+        &[
+            "diff",
+            "--name-only",
+            "-z",
+            "--relative",
+            "--find-renames",
+            "--diff-filter=d",
+            &range,
+            "--",
+        ],
+    )?;
+    let names: BTreeSet<String> = output
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(existing_files(repo_root, names))
+}
+
+/// Files under `repo_root` that git tracks (`git ls-files`), in path order.
+///
+/// Tracked files deleted from the working tree are skipped.
+pub fn tracked_files(repo_root: &Path) -> Result<Vec<PathBuf>> {
+    ensure_work_tree(repo_root, "--tracked")?;
+    let output = run_git(repo_root, &["ls-files", "-z"])?;
+    let names: BTreeSet<String> = output
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(existing_files(repo_root, names))
+}
+
+fn existing_files(repo_root: &Path, names: impl IntoIterator<Item = String>) -> Vec<PathBuf> {
+    names
+        .into_iter()
+        .map(|name| repo_root.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Map every changed file under `repo_root` to its status with a single `git status` call.
+///
+/// Files missing from the map are clean. `repo_root` may sit below the git top level.
+pub fn status_map(repo_root: &Path) -> Result<HashMap<PathBuf, FileStatus>> {
+    // Porcelain paths are relative to the top level; the prefix leads from there to repo_root.
+    let prefix = run_git(repo_root, &["rev-parse", "--show-prefix"])?;
+    let output = run_git(
+        repo_root,
+        &["status", "--porcelain", "-z", "--untracked-files=all"],
+    )?;
+    Ok(parse_porcelain(&output, repo_root, prefix.trim_end()))
+}
+
+/// Look up a file's status, treating files absent from `statuses` as clean.
+pub fn status_of(statuses: &HashMap<PathBuf, FileStatus>, path: &Path) -> FileStatus {
+    statuses.get(path).copied().unwrap_or(FileStatus::Clean)
+}
+
+/// Parse `git status --porcelain -z`, keeping entries under `prefix` (the path from the
+/// git top level to `repo_root`) and keying them by their path under `repo_root`.
+fn parse_porcelain(output: &str, repo_root: &Path, prefix: &str) -> HashMap<PathBuf, FileStatus> {
+    let mut statuses = HashMap::new();
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+
+        let (code, path) = entry.split_at(3);
+        let mut code = code.chars();
+        let index = code.next().unwrap_or(' ');
+        let worktree = code.next().unwrap_or(' ');
+
+        // Renames and copies are followed by the original path, which we skip.
+        if matches!(index, 'R' | 'C') {
+            entries.next();
+        }
+
+        let status = if index == '?' {
+            FileStatus::Untracked
+        } else if worktree != ' ' {
+            FileStatus::Modified
+        } else {
+            FileStatus::Staged
+        };
+        if let Some(path) = path.strip_prefix(prefix) {
+            statuses.insert(repo_root.join(path), status);
+        }
+    }
+
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn git(root: &Path, args: &[&str]) {
+        let mut full = vec!["-c", "user.name=gf", "-c", "user.email=gf@example.com"];
+        full.extend_from_slice(args);
+        run_git(root, &full).unwrap();
+    }
+
+    #[test]
+    fn tracked_files_skip_untracked_and_deleted_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        assert!(
+            tracked_files(root)
+                .unwrap_err()
+                .to_string()
+                .starts_with("--tracked needs a git repository")
+        );
+
+        git(root, &["init", "-q"]);
+        fs::create_dir_all(root.join("src")).unwrap();
+        for name in ["src/lib.rs", "README.md", "gone.rs", "scratch.txt"] {
+            fs::write(root.join(name), "v1").unwrap();
+        }
+        git(root, &["add", "src/lib.rs", "README.md", "gone.rs"]);
+        fs::remove_file(root.join("gone.rs")).unwrap();
+
+        assert_eq!(
+            tracked_files(root).unwrap(),
+            vec![root.join("README.md"), root.join("src/lib.rs")]
+        );
+    }
+
+    #[test]
+    fn changed_files_merges_staged_and_unstaged() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        assert!(
+            changed_files(root)
+                .unwrap_err()
+                .to_string()
+                .starts_with("--changed needs a git repository")
+        );
+
+        git(root, &["init", "-q"]);
+        for name in ["a.rs", "b.rs", "c.rs", "gone.rs"] {
+            fs::write(root.join(name), "v1").unwrap();
+        }
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "init"]);
+
+        fs::write(root.join("a.rs"), "v2").unwrap();
+        fs::write(root.join("b.rs"), "v2").unwrap();
+        git(root, &["add", "b.rs"]);
+        fs::write(root.join("b.rs"), "v3").unwrap();
+        fs::write(root.join("new.rs"), "new").unwrap();
+        git(root, &["add", "new.rs"]);
+        fs::remove_file(root.join("gone.rs")).unwrap();
+
+        assert_eq!(
+            changed_files(root).unwrap(),
+            vec![root.join("a.rs"), root.join("b.rs"), root.join("new.rs")]
+        );
+    }
+
+    #[test]
+    fn status_map_handles_a_root_below_the_git_top_level() {
+        let dir = tempdir().unwrap();
+        let top = dir.path();
+        git(top, &["init", "-q"]);
+        let root = top.join("app");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "v1").unwrap();
+        fs::write(top.join("outside.rs"), "v1").unwrap();
+
+        let statuses = status_map(&root).unwrap();
+        assert_eq!(
+            status_of(&statuses, &root.join("src/lib.rs")),
+            FileStatus::Untracked
+        );
+        assert_eq!(statuses.len(), 1);
+    }
+
+    #[test]
+    fn files_changed_since_follows_renames_and_skips_deletions() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        for name in ["keep.rs", "old.rs", "gone.rs"] {
+            fs::write(
+                root.join(name),
+                format!("{name} has enough content to be a rename\n"),
+            )
+            .unwrap();
+        }
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "base"]);
+        git(root, &["tag", "base"]);
+
+        git(root, &["mv", "old.rs", "renamed.rs"]);
+        git(root, &["rm", "-q", "gone.rs"]);
+        fs::write(root.join("added.rs"), "added").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "work"]);
+
+        assert_eq!(
+            files_changed_since(root, "base").unwrap(),
+            vec![root.join("added.rs"), root.join("renamed.rs")]
+        );
+        let err = files_changed_since(root, "no-such-branch").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("'no-such-branch' does not resolve")
+        );
+    }
+
+    #[test]
+    fn parses_porcelain_statuses() {
+        let root = Path::new("/repo");
+        let output = " M src/a.rs\0M  src/b.rs\0MM src/c.rs\0?? notes.md\0R  new.rs\0old.rs\0";
+        let statuses = parse_porcelain(output, root, "");
+
+        assert_eq!(statuses[&root.join("src/a.rs")], FileStatus::Modified);
+        assert_eq!(statuses[&root.join("src/b.rs")], FileStatus::Staged);
+        assert_eq!(statuses[&root.join("src/c.rs")], FileStatus::Modified);
+        assert_eq!(statuses[&root.join("notes.md")], FileStatus::Untracked);
+        assert_eq!(statuses[&root.join("new.rs")], FileStatus::Staged);
+        assert!(!statuses.contains_key(&root.join("old.rs")));
+        assert_eq!(
+            status_of(&statuses, &root.join("README.md")),
+            FileStatus::Clean
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Gather files, stitch them together, and render them as one bundle.
+//!
+//! The `gf` binary resolves its targets with [`target::determine_target`], narrows them
+//! with [`filter::filter_files`], renders them with [`gather::render_files`] (or
+//! [`split::write_bundle`]), and hands the result to a [`deliver::Destination`].
+//! [`gather_to_string`] runs the resolve and render steps only, without the binary's extra
+//! filters (git changes, `--grep`, size caps) or clipboard handling.
+//!
+//! ```no_run
+//! use gather_files::{ConfigFile, GatherOptions, gather_to_string};
+//! use std::path::Path;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let root = Path::new("/path/to/repo");
+//! let options = GatherOptions {
+//!     root: root.to_path_buf(),
+//!     config: ConfigFile::load(&root.join(".gather-files.yaml"))?,
+//!     ..GatherOptions::default()
+//! };
+//! let bundle = gather_to_string(&["src", "docs"], &options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod clipboard;
+pub mod color;
+pub mod config;
+pub mod deliver;
+pub mod deps;
+pub mod filter;
+pub mod gather;
+pub mod git;
+pub mod manifest;
+pub mod since;
+pub mod split;
+pub mod target;
+pub mod tokens;
+pub mod transform;
+pub mod warnings;
+
+pub use config::{ConfigFile, Preset};
+pub use gather::{
+    RenderOptions, WalkOptions, collect_from_path, collect_from_preset, render_files,
+};
+
+use anyhow::Result;
+use std::path::PathBuf;
+use warnings::Warnings;
+
+/// Where and how [`gather_to_string`] gathers.
+#[derive(Debug, Default)]
+pub struct GatherOptions {
+    /// Directory that relative targets, preset bases, and section headers are relative to.
+    pub root: PathBuf,
+    /// Config whose presets targets may name, and whose `default` preset applies when no
+    /// target is given.
+    pub config: Option<ConfigFile>,
+    /// Newline-delimited paths that a `-` target gathers, in place of `gf`'s stdin.
+    pub file_list: String,
+    pub walk: WalkOptions,
+    pub render: RenderOptions,
+}
+
+/// Gather every target and render them as one bundle, as `gf` would before copying.
+///
+/// Targets mean what they do on the command line: an existing file or directory (relative
+/// to `options.root`), a preset name in `options.config`, or `-` for `options.file_list`.
+/// Files gathered by more than one target appear once. Warnings, such as for skipped
+/// binary files, are dropped.
+pub fn gather_to_string(targets: &[&str], options: &GatherOptions) -> Result<String> {
+    let mut warnings = Warnings::default();
+    let targets: Vec<String> = targets.iter().map(|target| target.to_string()).collect();
+    let target = target::determine_target(
+        &targets,
+        &options.root,
+        options.config.as_ref(),
+        &options.walk,
+        &mut options.file_list.as_bytes(),
+        &mut warnings,
+    )?;
+    let files = gather::dedup_files(target.files);
+    let (output, _) = render_files(&files, &options.root, &options.render, &mut warnings)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn gather_to_string_renders_paths_and_presets_once() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        fs::write(root.join("notes.md"), "notes\n").unwrap();
+
+        let config: ConfigFile =
+            serde_yaml::from_str("version: 1\npresets:\n  rust:\n    include: [\"**/*.rs\"]\n")
+                .unwrap();
+        let options = GatherOptions {
+            root: root.to_path_buf(),
+            config: Some(config),
+            ..GatherOptions::default()
+        };
+        let output = gather_to_string(&["rust", "src", "notes.md"], &options).unwrap();
+        assert_eq!(
+            output,
+            "-------\n# src/lib.rs\n\npub fn a() {}\n\n-------\n# notes.md\n\nnotes\n\n"
+        );
+
+        let err = gather_to_string(&["docs"], &options).unwrap_err();
+        assert_eq!(err.to_string(), "preset 'docs' not found in config");
+    }
+
+    #[test]
+    fn gather_to_string_uses_the_default_preset_without_targets() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("notes.md"), "notes\n").unwrap();
+
+        let config: ConfigFile = serde_yaml::from_str(
+            "version: 2\ndefault: rust\npresets:\n  rust:\n    include: [\"*.rs\"]\n",
+        )
+        .unwrap();
+        let options = GatherOptions {
+            root: root.to_path_buf(),
+            config: Some(config),
+            ..GatherOptions::default()
+        };
+        assert_eq!(
+            gather_to_string(&[], &options).unwrap(),
+            "-------\n# a.rs\n\nfn a() {}\n\n"
+        );
+    }
+}
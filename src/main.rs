@@ -1,21 +1,41 @@
-mod clipboard;
-mod config;
-mod gather;
+use gather_files::deliver::Destination;
+use gather_files::filter::{Filters, check_max_files, filter_files};
+use gather_files::target::{Target, determine_target, find_preset, parse_target_path};
+use gather_files::{
+    clipboard, color, config, deps, gather, git, manifest, since, split, tokens, transform,
+    warnings,
+};
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use config::ConfigFile;
-use gather::{collect_from_path, collect_from_preset, render_files};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use color::{ColorChoice, Painter};
+use config::{ConfigFile, Preset};
+use gather::{
+    DEFAULT_TEST_PATTERNS, OutputFormat, RenderOptions, SectionSize, SizeLimits, SortMode,
+    TestFilter, VirtualFile, WalkOptions, collect_from_preset, count_rendered, dedup_files,
+    display_path, load_gatherignore, render_bundle, resolve_base,
+};
+use indexmap::IndexMap;
+use regex::Regex;
+use split::{ChunkUnit, Layout};
+use std::collections::HashSet;
 use std::fs::OpenOptions;
-use std::io::{Write, stdout};
+use std::io::{BufRead, Write, stderr, stdin, stdout};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
+use tokens::TextCounts;
+use transform::{LineEndings, LineLimit};
+use warnings::Warnings;
 
 const CONFIG_FILE_NAME: &str = ".gather-files.yaml";
 
 /// GitHub repository for releases
 const GITHUB_REPO: &str = "BrianSigafoos/gather-files";
 
+/// Files or directories that mark the top of a project, checked while searching upward.
+const ROOT_MARKERS: &[&str] = &[".git", ".hg", ".jj", "pnpm-workspace.yaml"];
+
 /// Install script URL
 const INSTALL_SCRIPT_URL: &str = "https://gf.bfoos.net/install.sh";
 
@@ -27,18 +47,336 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
 
-    /// Optional target (directory path or preset name)
-    target: Option<String>,
+    /// Targets to gather: directory paths, preset names, or `-` to read a file list from stdin
+    target: Vec<String>,
+
+    /// Path to a YAML or TOML config file (default: the nearest .gather-files.yaml at or above the current directory)
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// Output format (defaults to the preset's `format`, then text)
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Collapse each file's leading import block into a single annotation line
+    #[arg(long)]
+    trim_imports: bool,
+
+    /// Drop comment lines (best effort, line-based; config keep_comments exempts extensions)
+    #[arg(long)]
+    strip_comments: bool,
+
+    /// Keep only the first N lines of each file, marking what was cut
+    #[arg(long, value_name = "N", conflicts_with = "tail")]
+    head: Option<usize>,
+
+    /// Keep only the last N lines of each file, marking what was cut
+    #[arg(long, value_name = "N")]
+    tail: Option<usize>,
+
+    /// Prefix each line with its line number, e.g. "  12 | let x = 1;"
+    #[arg(long, conflicts_with_all = ["trim_imports", "strip_comments"])]
+    line_numbers: bool,
+
+    /// Pipe files with this extension through a shell command, e.g. ipynb='jupyter nbconvert
+    /// --to script --stdin --stdout' (repeatable; overrides config content_filters)
+    #[arg(long, value_name = "EXT=CMD", value_parser = parse_content_filter)]
+    content_filter: Vec<(String, String)>,
+
+    /// Fail instead of gathering when more than this many files match (--dry-run still lists them)
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Skip files larger than this (e.g. 500KB, 1MB; K/M/G are powers of 1024)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_file_size: Option<u64>,
+
+    /// Stop gathering once the files so far would exceed this total (e.g. 10MB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_total_size: Option<u64>,
+
+    /// Include binary files as base64 instead of skipping them
+    #[arg(long)]
+    include_binary: bool,
+
+    /// Leave out the "[gf: skipped ... — reason]" markers for omitted content
+    #[arg(long)]
+    no_annotations: bool,
+
+    /// Normalize line endings in file contents
+    #[arg(long, value_enum, default_value_t = LineEndings::Keep)]
+    line_endings: LineEndings,
+
+    /// Walk directories without honoring .gitignore, .git/info/exclude, or the global gitignore
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Keep only files modified within this window, e.g. 30m, 2h, 3d, or 1w
+    #[arg(long, value_name = "DURATION", value_parser = since::parse_duration)]
+    modified_within: Option<Duration>,
+
+    /// Keep only files whose contents match this regex
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+    grep: Option<Regex>,
+
+    /// Leave out empty and whitespace-only files
+    #[arg(long)]
+    skip_empty: bool,
+
+    /// Reorder gathered files; a promoted README stays first
+    #[arg(long, value_enum, value_name = "MODE")]
+    sort: Option<SortMode>,
+
+    /// Keep files in walk/pattern order instead of moving a README to the front
+    #[arg(long)]
+    no_promote_readme: bool,
+
+    /// Reverse the --sort order (e.g. newest first with --sort mtime)
+    #[arg(long, requires = "sort")]
+    reverse: bool,
+
+    /// Walk into dotfiles and dot-directories such as .github (ignore_dirs still applies)
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// Ignore the repo's .gatherignore file
+    #[arg(long)]
+    no_gatherignore: bool,
+
+    /// Gather files matching this glob without a preset; a target, if given, is the base (repeatable)
+    #[arg(
+        long,
+        value_name = "GLOB",
+        value_parser = parse_glob,
+        conflicts_with_all = ["manifest", "entrypoint", "interactive"]
+    )]
+    include: Vec<String>,
+
+    /// Fail when a preset include pattern matches no files (by default it's a warning)
+    #[arg(long)]
+    strict: bool,
+
+    /// Print config lookup, target, base, and per-pattern match counts to stderr
+    #[arg(long)]
+    verbose: bool,
+
+    /// Descend into symlinked directories (symlink cycles are skipped)
+    #[arg(long)]
+    follow_links: bool,
+
+    /// Walk at most N levels below the target directory or preset base (1 = its direct children)
+    #[arg(long, value_name = "N", value_parser = parse_depth)]
+    max_depth: Option<usize>,
+
+    /// Show file headers relative to this directory instead of the repo root
+    #[arg(long, value_name = "DIR")]
+    relative_to: Option<String>,
+
+    /// Match preset and --include globs against this directory instead of the configured base
+    #[arg(long, value_name = "DIR")]
+    base: Option<String>,
+
+    /// Skip files matching this glob (relative to the preset base or walked directory; repeatable)
+    #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+    exclude: Vec<String>,
+
+    /// Gather only files with staged or unstaged git changes (within the targets, if given)
+    #[arg(long, conflicts_with = "since")]
+    changed: bool,
+
+    /// Gather only files git tracks (within the targets, if given)
+    #[arg(long, conflicts_with_all = ["changed", "since"])]
+    tracked: bool,
+
+    /// Gather only files changed since a git ref, e.g. main (git diff REF...HEAD)
+    #[arg(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Skip test files (tests/, spec/, *_test.*, *.test.*, test_*.py, ...)
+    #[arg(long, conflicts_with = "only_tests")]
+    exclude_tests: bool,
+
+    /// Gather only test files (same heuristics as --exclude-tests)
+    #[arg(long)]
+    only_tests: bool,
+
+    /// Print the files a preset matches and exit, without reading or copying them
+    #[arg(long, value_name = "PRESET", conflicts_with = "target")]
+    resolve_preset: Option<String>,
+
+    /// Annotate listed files with their git status (modified, staged, untracked, clean)
+    #[arg(long)]
+    include_git_status: bool,
+
+    /// Wrap the entire output in a single fenced code block
+    #[arg(long)]
+    wrap_all: bool,
+
+    /// Prepend an ASCII tree of the gathered files
+    #[arg(long)]
+    tree: bool,
+
+    /// Prepend a numbered list of gathered files with their char counts
+    #[arg(long)]
+    toc: bool,
+
+    /// Include piped stdin as a virtual file with this header name
+    #[arg(long, value_name = "NAME")]
+    stdin_as: Option<String>,
+
+    /// Ignore the global config ($XDG_CONFIG_HOME/gather-files/config.yaml)
+    #[arg(long, global = true)]
+    no_global: bool,
+
+    /// Extra file or directory name marking the project root (repeatable; .git, .hg, .jj,
+    /// pnpm-workspace.yaml, and package.json workspaces are built in)
+    #[arg(long, global = true, value_name = "NAME")]
+    root_marker: Vec<String>,
+
+    /// When to use colors in summary, listing, and error output
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Copy with this clipboard backend only, skipping auto-detection
+    #[arg(long, value_name = "BACKEND", value_parser = clap::builder::PossibleValuesParser::new(clipboard::backend_names()))]
+    clipboard: Option<String>,
+
+    /// Print the gathered output to stdout instead of copying it (status goes to stderr)
+    #[arg(long, conflicts_with = "output")]
+    stdout: bool,
+
+    /// Collect and render as usual but skip the copy, producing no bundle output at all
+    #[arg(long, conflicts_with_all = ["stdout", "output", "clipboard"])]
+    no_clipboard: bool,
+
+    /// Don't print the summary line; "No files found" goes to stderr (errors still print)
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Warn when copying a bundle larger than this to the clipboard
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "1MB")]
+    clipboard_warn_size: u64,
+
+    /// Fail instead of copying a bundle larger than this to the clipboard
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_clipboard_size: Option<u64>,
+
+    /// Write the gathered output to this file instead of copying it
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Add to the end of the --output file instead of replacing it
+    #[arg(long, requires = "output")]
+    append: bool,
+
+    /// Also report the bundle's word count in the summary
+    #[arg(long)]
+    count_words: bool,
 
-    /// Path to config file (.gather-files.yaml)
-    #[arg(long, default_value = CONFIG_FILE_NAME)]
-    config: String,
+    /// Also report the bundle's line count in the summary
+    #[arg(long)]
+    count_lines: bool,
+
+    /// Also report an approximate LLM token count in the summary
+    #[arg(long)]
+    tokens: bool,
+
+    /// Print each file's char count and share of the total to stderr, largest first
+    #[arg(long)]
+    stats: bool,
+
+    /// After the run, write totals, per-file chars, tokens, and timing as JSON (`-` for stderr)
+    #[arg(long, value_name = "PATH")]
+    stats_json: Option<String>,
+
+    /// Render files whose contents repeat an earlier file as a header naming that file
+    #[arg(long)]
+    dedup_content: bool,
+
+    /// Place this file's contents verbatim before the bundle (e.g. a prompt)
+    #[arg(long, value_name = "FILE")]
+    prepend: Option<String>,
+
+    /// Place this file's contents verbatim after the bundle
+    #[arg(long, value_name = "FILE")]
+    append_file: Option<String>,
+
+    /// Print how long collecting, rendering, and copying took to stderr, with the file count
+    #[arg(long)]
+    profile: bool,
+
+    /// Print the first N lines of the output to stderr before copying
+    #[arg(long, value_name = "N")]
+    preview: Option<usize>,
+
+    /// List the files that would be gathered, relative to the repo root, without reading them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print the absolute path of every file that would be gathered, one per line, and exit
+    #[arg(long)]
+    list_files: bool,
+
+    /// Gather a file plus the local files it references (mod, import, require, #include)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["target", "manifest"])]
+    entrypoint: Option<String>,
+
+    /// Maximum reference hops to follow from --entrypoint (default: full closure)
+    #[arg(long, value_name = "N", requires = "entrypoint")]
+    depth: Option<usize>,
+
+    /// Pick a preset from an interactive list when no target is given
+    #[arg(short, long, conflicts_with = "target")]
+    interactive: bool,
+
+    /// Gather exactly the files listed in a manifest (one path per line)
+    #[arg(long, value_name = "FILE", conflicts_with = "target")]
+    manifest: Option<String>,
+
+    /// Write the ordered list of gathered files to a manifest after the run
+    #[arg(long, value_name = "FILE")]
+    manifest_out: Option<String>,
+
+    /// Gather only files modified since the last run that used this marker file, then update it
+    #[arg(long, value_name = "FILE")]
+    since_file: Option<String>,
+
+    /// Split output into part files of at most this many bytes (never splitting a file)
+    #[arg(long, value_name = "BYTES", requires = "output_dir")]
+    split_output: Option<usize>,
+
+    /// Write the bundle as numbered chunks of at most this size (in --chunk-by units) named
+    /// after --output (default bundle.txt: bundle.001.txt, ...), never splitting a file
+    #[arg(long, value_name = "N", conflicts_with_all = ["split_output", "append"])]
+    chunk_size: Option<usize>,
+
+    /// Unit for --chunk-size
+    #[arg(long, value_enum, default_value_t = ChunkUnit::Chars, requires = "chunk_size")]
+    chunk_by: ChunkUnit,
+
+    /// Directory for --split-output part files
+    #[arg(long, value_name = "DIR", requires = "split_output")]
+    output_dir: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Create a starter .gather-files.yaml config file
     Init,
+    /// Check that the config file loads and every preset is well-formed, without gathering
+    #[command(alias = "config-check")]
+    Validate,
+    /// List the presets in the config with their patterns and base directories
+    List,
+    /// Print the effective config after merging, inheritance, and base resolution
+    Show,
+    /// Print the JSON schema for the config file
+    Schema,
+    /// Print a shell completion script (bash, zsh, fish, powershell, or elvish)
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
     /// Update gf to the latest version
     Upgrade {
         /// Check for updates without installing
@@ -48,109 +386,1101 @@ enum Command {
 }
 
 fn main() -> Result<()> {
-    if let Err(err) = run() {
-        eprintln!("error: {err:#}");
+    let cli = Cli::parse();
+    let errors = Painter::stderr(cli.color);
+    let mut warnings = Warnings::default();
+    let result = run(cli, &mut warnings);
+    print_notes(&mut warnings);
+    eprint!("{}", warnings.render(errors));
+    if let Err(err) = result {
+        eprintln!("{}: {err:#}", errors.red("error"));
         std::process::exit(1);
     }
     Ok(())
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+fn run(cli: Cli, warnings: &mut Warnings) -> Result<()> {
+    let painter = Painter::stdout(cli.color);
 
-    match cli.command {
-        Some(Command::Init) => {
-            run_init()?;
-            return Ok(());
-        }
-        Some(Command::Upgrade { check }) => {
-            run_upgrade(check)?;
-            return Ok(());
-        }
-        None => {}
+    if let Some(command) = &cli.command {
+        return run_command(command, &cli, painter);
     }
 
     let start = Instant::now();
-    let current_dir =
-        std::env::current_dir().context("failed to determine current working directory")?;
-    let repo_root = find_repo_root(&current_dir).unwrap_or(current_dir.clone());
-    let config_path = resolve_config_path(&repo_root, &cli.config);
-    let config = ConfigFile::load(&config_path)
-        .with_context(|| format!("failed to load config from {}", config_path.display()))?;
+    let started_at = SystemTime::now();
+    let (repo_root, config_path) = locate_project(&cli.root_marker, cli.config.as_deref())?;
+    let config = load_config(&config_path, cli.no_global).with_context(|| {
+        format!(
+            "config found at {} but could not be loaded",
+            config_path.display()
+        )
+    })?;
+    if cli.verbose {
+        let global = (!cli.no_global).then(config::global_config_path).flatten();
+        for line in config_search_report(&config_path, global.as_deref(), cli.no_global) {
+            eprintln!("verbose: {line}");
+        }
+    }
+
+    if let Some(name) = &cli.resolve_preset {
+        return run_resolve_preset(
+            name,
+            &repo_root,
+            config.as_ref(),
+            &walk_options(&cli, &repo_root, config.as_ref())?,
+            cli.include_git_status,
+            painter,
+            warnings,
+        );
+    }
+
+    let Target {
+        files,
+        description,
+        format,
+    } = select_target(&cli, &repo_root, config.as_ref(), warnings)?;
+    let files = dedup_files(files);
+    print_notes(warnings);
+    if cli.verbose {
+        eprintln!("verbose: target {description}: {} files", files.len());
+    }
+    let since_marker = cli
+        .since_file
+        .as_ref()
+        .map(|marker| parse_target_path(marker, &repo_root));
+    let filters = filters(&cli, config.as_ref(), since_marker.as_deref(), started_at)?;
+    let filtered = filter_files(files, &filters, &repo_root, warnings)?;
+    let files = &filtered.files;
+    let collect_time = start.elapsed();
+
+    if cli.list_files {
+        return print_absolute_paths(files);
+    }
 
-    let (files, description) = determine_target(&cli.target, &repo_root, config.as_ref())?;
+    let display_root = display_root(cli.relative_to.as_deref(), &repo_root)?;
 
-    if files.is_empty() {
-        println!("No files found for {}.", description);
+    if cli.dry_run {
+        print_file_list(files, &repo_root, cli.include_git_status, painter)?;
+        println!("{} files would be gathered ({}).", files.len(), description);
+        if cli.stats {
+            let options = render_options(&cli, config.as_ref(), format, Vec::new());
+            let rendered = render_bundle(files, &display_root, &options, warnings)?;
+            eprint!("{}", render_stats(&rendered.sections));
+        }
         return Ok(());
     }
 
-    let (rendered, char_count) = render_files(&files, &repo_root)?;
-    clipboard::copy_to_clipboard(&rendered)?;
+    check_max_files(files.len(), cli.max_files, &description)?;
+
+    let virtual_files = read_stdin_file(cli.stdin_as.as_deref())?;
+    let status = if status_on_stderr(&cli) {
+        Painter::stderr(cli.color)
+    } else {
+        painter
+    };
+    if files.is_empty() && virtual_files.is_empty() {
+        print_status(
+            status_on_stderr(&cli),
+            &status.yellow(&format!("No files found for {}.", description)),
+        );
+        return Ok(());
+    }
+
+    let mut render_options = render_options(&cli, config.as_ref(), format, virtual_files);
+    render_options.omissions = filtered.trim.omissions.clone();
+    render_options.prepend = read_bookend(cli.prepend.as_deref(), "--prepend", &repo_root)?;
+    render_options.append = read_bookend(cli.append_file.as_deref(), "--append-file", &repo_root)?;
+    let destination = choose_destination(&cli, &repo_root, config.as_ref());
+    let bundle = Bundle {
+        files,
+        display_root: &display_root,
+        options: &render_options,
+        description: &description,
+    };
+    let delivery = deliver_bundle(&cli, &bundle, &repo_root, &destination, warnings)?;
+    if cli.stats {
+        eprint!("{}", render_stats(&delivery.sections));
+    }
+
+    let render_time = start.elapsed() - collect_time - delivery.deliver_time;
+    let summary = filtered.notes(delivery.summary.clone());
+
+    if let Some(manifest_out) = &cli.manifest_out {
+        let manifest_path = parse_target_path(manifest_out, &repo_root);
+        manifest::write_manifest(&manifest_path, files, &repo_root)?;
+    }
+
+    // Record when this run started so edits made while gathering are picked up next time.
+    if let Some(marker) = &since_marker {
+        since::write_marker(marker, started_at)?;
+    }
 
     let elapsed = start.elapsed();
-    println!(
-        "Copied {} chars from {} files ({}) in {:.2?}.",
-        char_count,
-        files.len(),
-        description,
-        elapsed
+    if let Some(target) = &cli.stats_json {
+        write_stats_json(target, &delivery, elapsed, &repo_root)?;
+    }
+    if cli.profile {
+        eprint!(
+            "{}",
+            render_profile(
+                &[
+                    ("collect", collect_time),
+                    ("render", render_time),
+                    (destination.verb(), delivery.deliver_time),
+                    ("total", elapsed),
+                ],
+                files.len(),
+            )
+        );
+    }
+    if !cli.quiet {
+        print_status(
+            cli.stdout,
+            &status.green(&format!("{} in {:.2?}.", summary, elapsed)),
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the `--verbose` notes collected so far.
+fn print_notes(warnings: &mut Warnings) {
+    for note in warnings.take_notes() {
+        eprintln!("verbose: {note}");
+    }
+}
+
+/// Run a subcommand (`gf init`, `gf validate`, ...) instead of gathering.
+fn run_command(command: &Command, cli: &Cli, painter: Painter) -> Result<()> {
+    match command {
+        Command::Init => run_init(),
+        Command::Validate => run_validate(cli.config.as_deref(), cli.no_global, &cli.root_marker),
+        Command::List => run_list(
+            cli.config.as_deref(),
+            cli.no_global,
+            &cli.root_marker,
+            painter,
+        ),
+        Command::Show => run_show(cli.config.as_deref(), cli.no_global, &cli.root_marker),
+        Command::Schema => {
+            let schema = serde_json::to_string_pretty(&ConfigFile::json_schema())
+                .context("failed to serialize config schema")?;
+            println!("{schema}");
+            Ok(())
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(*shell, &mut Cli::command(), "gf", &mut stdout());
+            Ok(())
+        }
+        Command::Upgrade { check } => run_upgrade(*check),
+    }
+}
+
+/// The files a run starts from: the target, narrowed to (or replaced by) git's changed or
+/// tracked files when `--changed`, `--tracked`, or `--since` is given.
+fn select_target(
+    cli: &Cli,
+    repo_root: &Path,
+    config: Option<&ConfigFile>,
+    warnings: &mut Warnings,
+) -> Result<Target> {
+    Ok(match git_changes(cli, repo_root)? {
+        Some((changed, label)) if !has_explicit_target(cli) => Target {
+            files: changed,
+            description: label,
+            format: None,
+        },
+        Some((changed, label)) => restrict_target(
+            resolve_target(cli, repo_root, config, warnings)?,
+            &changed,
+            &label,
+        ),
+        None => resolve_target(cli, repo_root, config, warnings)?,
+    })
+}
+
+/// The filters this run applies: tests, `--since-file` and `--modified-within`, `--grep`,
+/// `--skip-empty`, `--sort`, and the size limits.
+fn filters(
+    cli: &Cli,
+    config: Option<&ConfigFile>,
+    since_marker: Option<&Path>,
+    started_at: SystemTime,
+) -> Result<Filters> {
+    let tests = if cli.exclude_tests {
+        Some(TestFilter::Exclude)
+    } else {
+        cli.only_tests.then_some(TestFilter::Only)
+    };
+    let test_patterns = match config.and_then(|config| config.test_patterns.clone()) {
+        Some(patterns) => patterns,
+        None => DEFAULT_TEST_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+    };
+    let mut modified_after = Vec::new();
+    if let Some(since) = since_marker.map(since::read_marker).transpose()?.flatten() {
+        modified_after.push(since);
+    }
+    if let Some(window) = cli.modified_within {
+        modified_after.push(since::window_start(started_at, window)?);
+    }
+    Ok(Filters {
+        tests,
+        test_patterns,
+        modified_after,
+        grep: cli.grep.clone(),
+        skip_empty: cli.skip_empty,
+        sort: cli.sort,
+        reverse: cli.reverse,
+        pin_readme: promote_readme_enabled(cli, config),
+        size_limits: SizeLimits {
+            max_file: cli.max_file_size,
+            max_total: cli.max_total_size,
+        },
+    })
+}
+
+/// Where this run's bundle (or, with `--chunk-size`/`--split-output`, its index) goes.
+fn choose_destination<'a>(
+    cli: &'a Cli,
+    repo_root: &Path,
+    config: Option<&'a ConfigFile>,
+) -> Destination<'a> {
+    // With --chunk-size, --output names the chunk files and the index is copied as usual.
+    let output = cli.output.as_ref().filter(|_| cli.chunk_size.is_none());
+    match (output, cli.stdout) {
+        (Some(output), _) => {
+            let path = parse_target_path(output, repo_root);
+            if cli.append {
+                Destination::AppendFile(path)
+            } else {
+                Destination::File(path)
+            }
+        }
+        (None, true) => Destination::Stdout,
+        (None, false) if cli.no_clipboard => Destination::Discard,
+        (None, false) => match &cli.clipboard {
+            Some(backend) => Destination::ClipboardBackend(backend),
+            None => {
+                Destination::Clipboard(config.and_then(|config| config.clipboard_order.as_deref()))
+            }
+        },
+    }
+}
+
+/// The gathered files and how to render them.
+struct Bundle<'a> {
+    files: &'a [PathBuf],
+    display_root: &'a Path,
+    options: &'a RenderOptions,
+    description: &'a str,
+}
+
+/// What rendering and delivering the bundle produced, for the summary and stats.
+struct Delivery {
+    summary: String,
+    char_count: usize,
+    token_count: Option<usize>,
+    sections: Vec<SectionSize>,
+    deliver_time: Duration,
+}
+
+/// Render the bundle whole, in chunks, or in parts, and send it (or the index) to `destination`.
+fn deliver_bundle(
+    cli: &Cli,
+    bundle: &Bundle,
+    repo_root: &Path,
+    destination: &Destination,
+    warnings: &mut Warnings,
+) -> Result<Delivery> {
+    let (limit, layout) = match (cli.chunk_size, cli.split_output, &cli.output_dir) {
+        (Some(limit), _, _) => {
+            let name = cli.output.as_deref().unwrap_or(DEFAULT_CHUNK_NAME);
+            let base = parse_target_path(name, repo_root);
+            (
+                limit,
+                Layout::Chunks {
+                    base,
+                    unit: cli.chunk_by,
+                },
+            )
+        }
+        (None, Some(limit), Some(output_dir)) => {
+            (limit, Layout::Dir(parse_target_path(output_dir, repo_root)))
+        }
+        _ => return deliver_whole(cli, bundle, destination, warnings),
+    };
+    deliver_parts(cli, bundle, limit, &layout, destination, warnings)
+}
+
+/// `--chunk-size` or `--split-output`: write numbered files and deliver their index.
+fn deliver_parts(
+    cli: &Cli,
+    bundle: &Bundle,
+    limit: usize,
+    layout: &Layout,
+    destination: &Destination,
+    warnings: &mut Warnings,
+) -> Result<Delivery> {
+    let written = split::write_bundle(
+        bundle.files,
+        bundle.display_root,
+        bundle.options,
+        limit,
+        layout,
+        warnings,
+    )?;
+    let deliver_time =
+        timed(|| destination.deliver(&split::render_index(&written.parts), warnings))?;
+    let counts = format!(
+        "{} from {} files ({})",
+        count_summary(
+            written.char_count,
+            cli.tokens.then_some(written.token_count),
+            requested_counts(cli, written.text_counts)
+        ),
+        count_rendered(&written.sections),
+        bundle.description
     );
+    let summary = match layout {
+        Layout::Dir(dir) => format!(
+            "Wrote {counts} to {} parts in {}",
+            written.parts.len(),
+            dir.display()
+        ),
+        Layout::Chunks { base, .. } => format!(
+            "Wrote {counts} to {} chunks named like {}",
+            written.parts.len(),
+            written
+                .parts
+                .first()
+                .map_or_else(|| base.display(), |part| part.path.display())
+        ),
+    };
+    Ok(Delivery {
+        summary,
+        char_count: written.char_count,
+        token_count: Some(written.token_count),
+        sections: written.sections,
+        deliver_time,
+    })
+}
 
+/// Render the whole bundle and deliver it in one piece.
+fn deliver_whole(
+    cli: &Cli,
+    bundle: &Bundle,
+    destination: &Destination,
+    warnings: &mut Warnings,
+) -> Result<Delivery> {
+    let rendered = render_bundle(bundle.files, bundle.display_root, bundle.options, warnings)?;
+    if let Some(lines) = cli.preview {
+        eprint!(
+            "{}",
+            render_preview(&rendered.output, lines, rendered.chars)
+        );
+    }
+    if destination.is_clipboard() {
+        clipboard::check_payload_size(
+            rendered.output.len(),
+            cli.clipboard_warn_size,
+            cli.max_clipboard_size,
+            warnings,
+        )?;
+    }
+    let deliver_time = timed(|| destination.deliver(&rendered.output, warnings))?;
+    let token_count =
+        (cli.tokens || cli.stats_json.is_some()).then(|| tokens::estimate_tokens(&rendered.output));
+    let text_counts = if cli.count_words || cli.count_lines {
+        tokens::count_text(&rendered.output)
+    } else {
+        TextCounts::default()
+    };
+    let counts = format!(
+        "{} from {} files ({})",
+        count_summary(
+            rendered.chars,
+            token_count.filter(|_| cli.tokens),
+            requested_counts(cli, text_counts)
+        ),
+        count_rendered(&rendered.sections),
+        bundle.description
+    );
+    let summary = match destination {
+        Destination::Clipboard(_) | Destination::ClipboardBackend(_) => format!("Copied {counts}"),
+        Destination::Stdout => format!("Printed {counts}"),
+        Destination::Discard => format!("Rendered {counts} (not copied)"),
+        Destination::File(path) => format!("Wrote {counts} to {}", path.display()),
+        Destination::AppendFile(path) => format!("Appended {counts} to {}", path.display()),
+    };
+    Ok(Delivery {
+        summary,
+        char_count: rendered.chars,
+        token_count,
+        sections: rendered.sections,
+        deliver_time,
+    })
+}
+
+/// Write `--stats-json` to `target`, or to stderr when it is `-`.
+fn write_stats_json(
+    target: &str,
+    delivery: &Delivery,
+    elapsed: Duration,
+    repo_root: &Path,
+) -> Result<()> {
+    let stats = render_stats_json(
+        &delivery.sections,
+        delivery.char_count,
+        delivery.token_count,
+        elapsed,
+    );
+    let json = serde_json::to_string_pretty(&stats).context("failed to serialize --stats-json")?;
+    if target == "-" {
+        eprintln!("{json}");
+        return Ok(());
+    }
+    let path = parse_target_path(target, repo_root);
+    std::fs::write(&path, format!("{json}\n"))
+        .with_context(|| format!("failed to write stats to {}", path.display()))
+}
+
+/// With --stdout the bundle owns stdout, and with --quiet stdout stays clean for scripts,
+/// so the remaining status lines move to stderr.
+fn status_on_stderr(cli: &Cli) -> bool {
+    cli.stdout || cli.quiet
+}
+
+fn print_status(to_stderr: bool, line: &str) {
+    if to_stderr {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+/// Rendering options for this run; the preset's `format` applies unless `--format` is given.
+fn render_options(
+    cli: &Cli,
+    config: Option<&ConfigFile>,
+    format: Option<OutputFormat>,
+    virtual_files: Vec<VirtualFile>,
+) -> RenderOptions {
+    RenderOptions {
+        format: cli.format.or(format).unwrap_or_default(),
+        trim_imports: cli.trim_imports,
+        line_numbers: cli.line_numbers,
+        line_limit: cli
+            .head
+            .map(LineLimit::Head)
+            .or(cli.tail.map(LineLimit::Tail)),
+        strip_comments: cli.strip_comments,
+        keep_comments: config
+            .map(|config| config.keep_comments.clone())
+            .unwrap_or_default(),
+        no_annotations: cli.no_annotations,
+        include_binary: cli.include_binary,
+        content_filters: content_filters(cli, config),
+        line_endings: cli.line_endings,
+        wrap_all: cli.wrap_all,
+        tree: cli.tree,
+        toc: cli.toc,
+        template: config
+            .and_then(|config| config.template.clone())
+            .unwrap_or_default(),
+        dedup_content: cli.dedup_content,
+        omissions: Vec::new(),
+        virtual_files,
+        prepend: None,
+        append: None,
+    }
+}
+
+/// Base name for `--chunk-size` files when `--output` isn't given.
+const DEFAULT_CHUNK_NAME: &str = "bundle.txt";
+
+/// Format `N chars`, then any requested word and line counts, with `(~T tokens)` appended
+/// when a token estimate was requested.
+fn count_summary(
+    char_count: usize,
+    token_count: Option<usize>,
+    (words, lines): (Option<usize>, Option<usize>),
+) -> String {
+    let mut summary = format!("{char_count} chars");
+    if let Some(words) = words {
+        summary.push_str(&format!(", {words} words"));
+    }
+    if let Some(lines) = lines {
+        summary.push_str(&format!(", {lines} lines"));
+    }
+    match token_count {
+        Some(tokens) => format!("{summary} (~{tokens} tokens)"),
+        None => summary,
+    }
+}
+
+/// The word and line counts `--count-words` and `--count-lines` ask the summary to show.
+fn requested_counts(cli: &Cli, counts: TextCounts) -> (Option<usize>, Option<usize>) {
+    (
+        cli.count_words.then_some(counts.words),
+        cli.count_lines.then_some(counts.lines),
+    )
+}
+
+fn print_absolute_paths(files: &[PathBuf]) -> Result<()> {
+    let mut out = stdout().lock();
+    for file in files {
+        let absolute = std::path::absolute(file)
+            .with_context(|| format!("failed to resolve {}", file.display()))?;
+        writeln!(out, "{}", absolute.display())?;
+    }
     Ok(())
 }
 
-fn determine_target(
-    target: &Option<String>,
+/// Machine-readable run stats for `--stats-json`: totals for the whole bundle, plus each
+/// file's section size in bundle order.
+fn render_stats_json(
+    sections: &[SectionSize],
+    char_count: usize,
+    token_count: Option<usize>,
+    elapsed: Duration,
+) -> serde_json::Value {
+    let files: Vec<serde_json::Value> = sections
+        .iter()
+        .filter(|section| !section.skipped)
+        .map(|section| serde_json::json!({ "path": section.display, "chars": section.chars }))
+        .collect();
+    serde_json::json!({
+        "files": files.len(),
+        "chars": char_count,
+        "tokens": token_count,
+        "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+        "per_file": files,
+    })
+}
+
+/// Table of each section's char count and percentage of the total, largest first.
+fn render_stats(sections: &[SectionSize]) -> String {
+    let mut rows: Vec<&SectionSize> = sections.iter().filter(|section| !section.skipped).collect();
+    let total: usize = rows.iter().map(|section| section.chars).sum();
+    rows.sort_by_key(|section| std::cmp::Reverse(section.chars));
+
+    let width = total.to_string().len().max("chars".len());
+    let mut table = format!("{:>width$}  {:>6}  path\n", "chars", "%");
+    for section in rows {
+        let percent = if total == 0 {
+            0.0
+        } else {
+            section.chars as f64 * 100.0 / total as f64
+        };
+        table.push_str(&format!(
+            "{:>width$}  {:>5.1}%  {}\n",
+            section.chars, percent, section.display
+        ));
+    }
+    table.push_str(&format!("{total:>width$}  {:>5.1}%  total\n", 100.0));
+    table
+}
+
+/// Run `f`, returning how long it took.
+fn timed(f: impl FnOnce() -> Result<()>) -> Result<Duration> {
+    let start = Instant::now();
+    f()?;
+    Ok(start.elapsed())
+}
+
+/// The `--profile` table: each phase's elapsed time, with the file count on the collect line.
+fn render_profile(phases: &[(&str, Duration)], file_count: usize) -> String {
+    let mut table = String::new();
+    for (idx, (phase, elapsed)) in phases.iter().enumerate() {
+        let millis = elapsed.as_secs_f64() * 1000.0;
+        table.push_str(&format!("profile: {phase:<8} {millis:>9.2}ms"));
+        if idx == 0 {
+            table.push_str(&format!("  ({file_count} files)"));
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// Show the head of the output with its total size, framed so it stands apart from it.
+fn render_preview(rendered: &str, lines: usize, char_count: usize) -> String {
+    let total_lines = rendered.lines().count();
+    let mut preview = format!(
+        "--- preview: first {} of {} lines ({} chars total) ---\n",
+        lines.min(total_lines),
+        total_lines,
+        char_count
+    );
+    for line in rendered.lines().take(lines) {
+        preview.push_str(line);
+        preview.push('\n');
+    }
+    preview.push_str("--- end preview ---\n");
+    preview
+}
+
+/// Parse a byte size such as `1500`, `500KB`, `1.5MB`, or `2G` (binary multiples).
+fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "unknown size unit in '{value}' (use B, KB, MB, or GB)"
+            ));
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{value}'"))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn parse_depth(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("depth must be at least 1".to_string()),
+        Ok(depth) => Ok(depth),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn parse_glob(value: &str) -> Result<String, String> {
+    globset::Glob::new(value)
+        .map(|_| value.to_string())
+        .map_err(|err| err.to_string())
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|err| err.to_string())
+}
+
+fn parse_content_filter(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((extension, command)) if !extension.is_empty() && !command.trim().is_empty() => Ok((
+            extension.trim_start_matches('.').to_string(),
+            command.to_string(),
+        )),
+        _ => Err(format!("expected EXT=COMMAND, got '{value}'")),
+    }
+}
+
+/// Merge config content filters with CLI ones, letting the CLI win per extension.
+fn content_filters(cli: &Cli, config: Option<&ConfigFile>) -> IndexMap<String, String> {
+    let mut filters = config
+        .map(|config| config.content_filters.clone())
+        .unwrap_or_default();
+    filters.extend(cli.content_filter.iter().cloned());
+    filters
+}
+
+fn read_stdin_file(name: Option<&str>) -> Result<Vec<VirtualFile>> {
+    let Some(name) = name else {
+        return Ok(Vec::new());
+    };
+
+    let contents = std::io::read_to_string(stdin()).context("failed to read stdin")?;
+    Ok(vec![VirtualFile {
+        name: name.to_string(),
+        contents,
+    }])
+}
+
+/// Read a `--prepend`/`--append-file` file verbatim.
+fn read_bookend(path: Option<&str>, flag: &str, repo_root: &Path) -> Result<Option<String>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let path = parse_target_path(path, repo_root);
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("failed to read {flag} file {}", path.display()))
+}
+
+/// Files selected by git-based flags, with a description, or `None` when none are set.
+fn git_changes(cli: &Cli, repo_root: &Path) -> Result<Option<(Vec<PathBuf>, String)>> {
+    if cli.changed {
+        return Ok(Some((
+            git::changed_files(repo_root)?,
+            "changed files".to_string(),
+        )));
+    }
+    if let Some(rev) = &cli.since {
+        return Ok(Some((
+            git::files_changed_since(repo_root, rev)?,
+            format!("files changed since {rev}"),
+        )));
+    }
+    if cli.tracked {
+        return Ok(Some((
+            git::tracked_files(repo_root)?,
+            "tracked files".to_string(),
+        )));
+    }
+    Ok(None)
+}
+
+fn has_explicit_target(cli: &Cli) -> bool {
+    !cli.target.is_empty()
+        || !cli.include.is_empty()
+        || cli.manifest.is_some()
+        || cli.entrypoint.is_some()
+        || cli.interactive
+}
+
+/// Gather `--include` globs through an ephemeral preset based at the target directory,
+/// `--base`, or the repo root.
+fn include_target(
+    cli: &Cli,
     repo_root: &Path,
     config: Option<&ConfigFile>,
-) -> Result<(Vec<PathBuf>, String)> {
-    match target {
-        None => {
-            let files = collect_from_path(repo_root)?;
-            Ok((files, format!("root {}", repo_root.display())))
+    warnings: &mut Warnings,
+) -> Result<Target> {
+    let walk = walk_options(cli, repo_root, config)?;
+    let base = match cli.target.as_slice() {
+        [] => walk.base.clone().unwrap_or_else(|| repo_root.to_path_buf()),
+        [_] if cli.base.is_some() => {
+            anyhow::bail!("pass the --include base as a target or with --base, not both")
         }
-        Some(argument) => {
-            let path_candidate = parse_target_path(argument, repo_root);
-            if path_candidate.exists() {
-                let files = collect_from_path(&path_candidate)?;
-                return Ok((files, format!("path {}", path_candidate.display())));
+        [directory] => {
+            let base = parse_target_path(directory, repo_root);
+            if !base.is_dir() {
+                anyhow::bail!(
+                    "with --include, the target must be a base directory: {}",
+                    base.display()
+                );
             }
+            base
+        }
+        _ => anyhow::bail!("--include takes at most one target (the base directory)"),
+    };
+
+    let preset = Preset {
+        extends: None,
+        include: cli.include.clone(),
+        exclude: Vec::new(),
+        base: Some(base.clone()),
+        from_file: None,
+        presets: Vec::new(),
+        format: None,
+        enabled: true,
+    };
+    Ok(Target {
+        files: collect_from_preset(
+            "--include",
+            &preset,
+            &IndexMap::new(),
+            repo_root,
+            &walk,
+            warnings,
+        )?,
+        description: format!(
+            "--include {} in {}",
+            cli.include.join(", "),
+            display_path(&base, repo_root)
+        ),
+        format: None,
+    })
+}
+
+/// Keep only the target's files that are in `allowed`, preserving the target's order.
+fn restrict_target(target: Target, allowed: &[PathBuf], label: &str) -> Target {
+    let canonical = |path: &PathBuf| std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+    let allowed: HashSet<PathBuf> = allowed.iter().map(canonical).collect();
+    Target {
+        files: target
+            .files
+            .into_iter()
+            .filter(|path| allowed.contains(&canonical(path)))
+            .collect(),
+        description: format!("{}, {} only", target.description, label),
+        format: target.format,
+    }
+}
+
+/// Resolve the run's files from a manifest, entrypoint, interactive pick, or target argument.
+fn resolve_target(
+    cli: &Cli,
+    repo_root: &Path,
+    config: Option<&ConfigFile>,
+    warnings: &mut Warnings,
+) -> Result<Target> {
+    if let Some(manifest) = &cli.manifest {
+        let manifest_path = parse_target_path(manifest, repo_root);
+        return Ok(Target {
+            files: manifest::read_manifest(&manifest_path, repo_root)?,
+            description: format!("manifest {}", manifest_path.display()),
+            format: None,
+        });
+    }
 
-            let config = config.ok_or_else(|| {
-                anyhow::anyhow!("no config found when looking for preset '{argument}'")
-            })?;
-            let preset = config
-                .preset(argument)
-                .ok_or_else(|| anyhow::anyhow!("preset '{argument}' not found in config"))?;
-            let files = collect_from_preset(argument, preset, repo_root)?;
-            Ok((files, format!("preset '{argument}'")))
+    if let Some(entrypoint) = &cli.entrypoint {
+        let entrypoint_path = parse_target_path(entrypoint, repo_root);
+        return Ok(Target {
+            files: deps::collect_dependencies(&entrypoint_path, repo_root, cli.depth)?,
+            description: format!("entrypoint {}", display_path(&entrypoint_path, repo_root)),
+            format: None,
+        });
+    }
+
+    if cli.target.iter().any(|target| target == "-") && cli.stdin_as.is_some() {
+        anyhow::bail!("--stdin-as cannot be combined with the '-' target; both read stdin");
+    }
+
+    if !cli.include.is_empty() {
+        return include_target(cli, repo_root, config, warnings);
+    }
+
+    let targets = if cli.interactive {
+        let config =
+            config.ok_or_else(|| anyhow::anyhow!("no config found to pick a preset from"))?;
+        vec![select_preset(config)?]
+    } else {
+        cli.target.clone()
+    };
+    determine_target(
+        &targets,
+        repo_root,
+        config,
+        &walk_options(cli, repo_root, config)?,
+        &mut stdin().lock(),
+        warnings,
+    )
+}
+
+fn walk_options(cli: &Cli, repo_root: &Path, config: Option<&ConfigFile>) -> Result<WalkOptions> {
+    let mut options = WalkOptions {
+        respect_gitignore: !cli.no_gitignore,
+        include_hidden: cli.include_hidden,
+        follow_links: cli.follow_links,
+        max_depth: cli.max_depth,
+        verbose: cli.verbose,
+        strict: cli.strict,
+        promote_readme: promote_readme_enabled(cli, config),
+        ..WalkOptions::default()
+    };
+    if let Some(config) = config {
+        options.ignored_dirs = config.ignored_dirs();
+        options.exclude = config.exclude.clone();
+    }
+    options.cli_exclude = cli.exclude.clone();
+    if let Some(base) = &cli.base {
+        let base = parse_target_path(base, repo_root);
+        if !base.is_dir() {
+            anyhow::bail!("--base is not a directory: {}", base.display());
         }
+        options.base = Some(base);
+    }
+    if !cli.no_gatherignore {
+        options.gatherignore = load_gatherignore(repo_root)?;
     }
+    Ok(options)
 }
 
-fn parse_target_path(argument: &str, repo_root: &Path) -> PathBuf {
-    let path = Path::new(argument);
-    if path.is_absolute() {
-        path.to_path_buf()
+/// README-first ordering is on unless `--no-promote-readme` or the config turns it off.
+fn promote_readme_enabled(cli: &Cli, config: Option<&ConfigFile>) -> bool {
+    !cli.no_promote_readme
+        && config
+            .and_then(|config| config.promote_readme)
+            .unwrap_or(true)
+}
+
+/// Print the files a preset resolves to, without reading or copying them.
+fn run_resolve_preset(
+    name: &str,
+    repo_root: &Path,
+    config: Option<&ConfigFile>,
+    walk: &WalkOptions,
+    include_git_status: bool,
+    painter: Painter,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    let (config, preset) = find_preset(config, name)?;
+    let files = collect_from_preset(name, preset, &config.presets, repo_root, walk, warnings)?;
+    print_file_list(&files, repo_root, include_git_status, painter)?;
+    println!("{} files matched preset '{}'.", files.len(), name);
+    Ok(())
+}
+
+/// Print repo-relative paths, one per line, optionally prefixed with a git status column.
+fn print_file_list(
+    files: &[PathBuf],
+    repo_root: &Path,
+    include_git_status: bool,
+    painter: Painter,
+) -> Result<()> {
+    let statuses = if include_git_status {
+        Some(git::status_map(repo_root)?)
     } else {
-        repo_root.join(path)
+        None
+    };
+
+    for file in files {
+        let display = display_path(file, repo_root);
+        match &statuses {
+            Some(statuses) => {
+                let status = git::status_of(statuses, file);
+                let label = format!("{:<9}", status.label());
+                println!("{}  {display}", paint_status(painter, status, &label));
+            }
+            None => println!("{display}"),
+        }
     }
+    Ok(())
 }
 
-fn resolve_config_path(repo_root: &Path, config: &str) -> PathBuf {
-    let path = Path::new(config);
-    if path.is_absolute() {
-        path.to_path_buf()
+fn paint_status(painter: Painter, status: git::FileStatus, text: &str) -> String {
+    match status {
+        git::FileStatus::Modified => painter.yellow(text),
+        git::FileStatus::Staged => painter.green(text),
+        git::FileStatus::Untracked => painter.red(text),
+        git::FileStatus::Clean => painter.dim(text),
+    }
+}
+
+fn select_preset(config: &ConfigFile) -> Result<String> {
+    let names: Vec<&str> = config
+        .enabled_presets()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    prompt_for_preset(&names, &mut stdin().lock(), &mut stderr())
+}
+
+/// Print a numbered list of presets and read a choice (number or name) from `input`.
+fn prompt_for_preset(
+    names: &[&str],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<String> {
+    if names.is_empty() {
+        anyhow::bail!("no presets defined in config");
+    }
+
+    for (idx, name) in names.iter().enumerate() {
+        writeln!(output, "  {}) {}", idx + 1, name)?;
+    }
+    write!(output, "Select a preset [1-{}]: ", names.len())?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .context("failed to read preset selection")?;
+    let answer = answer.trim();
+
+    let selected = match answer.parse::<usize>() {
+        Ok(number) if (1..=names.len()).contains(&number) => Some(names[number - 1]),
+        _ => names.iter().copied().find(|name| *name == answer),
+    };
+
+    selected
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("invalid preset selection '{answer}'"))
+}
+
+/// Directory that rendered file headers are shown relative to: `--relative-to`, resolved
+/// against the repo root, or the repo root itself.
+fn display_root(relative_to: Option<&str>, repo_root: &Path) -> Result<PathBuf> {
+    let Some(relative_to) = relative_to else {
+        return Ok(repo_root.to_path_buf());
+    };
+    let root = parse_target_path(relative_to, repo_root);
+    if !root.is_dir() {
+        anyhow::bail!("--relative-to is not a directory: {}", root.display());
+    }
+    Ok(root)
+}
+
+/// Load the config at `config_path`, layered over the global config unless `no_global`.
+fn load_config(config_path: &Path, no_global: bool) -> Result<Option<ConfigFile>> {
+    let global = if no_global {
+        None
     } else {
-        repo_root.join(path)
+        config::global_config_path()
+    };
+    ConfigFile::load_merged(global.as_deref(), config_path)
+}
+
+/// Describe where `--verbose` looked for config files and which ones were loaded.
+fn config_search_report(config_path: &Path, global: Option<&Path>, no_global: bool) -> Vec<String> {
+    let describe = |path: &Path| {
+        let state = if path.is_file() {
+            "loaded"
+        } else {
+            "not found"
+        };
+        format!("{} ({state})", path.display())
+    };
+    let global = match global {
+        Some(path) => describe(path),
+        None if no_global => "skipped (--no-global)".to_string(),
+        None => "no location (HOME and XDG_CONFIG_HOME are unset)".to_string(),
+    };
+    vec![
+        format!("config {}", describe(config_path)),
+        format!("global config {global}"),
+    ]
+}
+
+/// Find the repo root and config path for a run started in the current directory.
+fn locate_project(extra_markers: &[String], config: Option<&str>) -> Result<(PathBuf, PathBuf)> {
+    let current_dir =
+        std::env::current_dir().context("failed to determine current working directory")?;
+    Ok(project_paths(&current_dir, extra_markers, config))
+}
+
+/// Pick the root that targets, preset bases, and headers resolve against, plus the config.
+///
+/// An explicit `--config` path is relative to the repo root. Otherwise the nearest config
+/// at or above `start` is used, and its directory becomes the root when it sits at or
+/// below the detected repo root (or no root marker was found), so presets resolve the
+/// same way from any subdirectory. A config above the repo root, such as one in a home
+/// directory, leaves the repo root alone.
+fn project_paths(
+    start: &Path,
+    extra_markers: &[String],
+    config: Option<&str>,
+) -> (PathBuf, PathBuf) {
+    let marker_root = find_repo_root(start, extra_markers);
+    if let Some(config) = config {
+        let root = marker_root.unwrap_or_else(|| start.to_path_buf());
+        let config_path = parse_target_path(config, &root);
+        return (root, config_path);
     }
+
+    let found = find_config_upward(start);
+    let config_dir = found.as_deref().and_then(Path::parent);
+    let root = match (marker_root, config_dir) {
+        (Some(root), Some(dir)) if dir.starts_with(&root) => dir.to_path_buf(),
+        (Some(root), _) => root,
+        (None, Some(dir)) => dir.to_path_buf(),
+        (None, None) => start.to_path_buf(),
+    };
+    let config_path = found.unwrap_or_else(|| root.join(CONFIG_FILE_NAME));
+    (root, config_path)
 }
 
-fn find_repo_root(start: &Path) -> Option<PathBuf> {
+fn find_config_upward(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+fn find_repo_root(start: &Path, extra_markers: &[String]) -> Option<PathBuf> {
     let mut current = Some(start);
     while let Some(dir) = current {
-        if dir.join(".git").exists() {
+        if is_repo_root(dir, extra_markers) {
             return Some(dir.to_path_buf());
         }
         current = dir.parent();
@@ -158,6 +1488,190 @@ fn find_repo_root(start: &Path) -> Option<PathBuf> {
     None
 }
 
+fn is_repo_root(dir: &Path, extra_markers: &[String]) -> bool {
+    let has_marker = ROOT_MARKERS
+        .iter()
+        .copied()
+        .chain(extra_markers.iter().map(String::as_str))
+        .any(|marker| dir.join(marker).exists());
+
+    has_marker || is_package_workspace_root(dir)
+}
+
+/// A `package.json` declaring `workspaces` marks a JavaScript monorepo root.
+fn is_package_workspace_root(dir: &Path) -> bool {
+    let Ok(contents) = std::fs::read_to_string(dir.join("package.json")) else {
+        return false;
+    };
+
+    serde_json::from_str::<serde_json::Value>(&contents)
+        .map(|package| package.get("workspaces").is_some())
+        .unwrap_or(false)
+}
+
+fn run_validate(config: Option<&str>, no_global: bool, root_markers: &[String]) -> Result<()> {
+    let (repo_root, config_path) = locate_project(root_markers, config)?;
+    let config = load_config(&config_path, no_global)
+        .with_context(|| format!("invalid config {}", config_path.display()))?
+        .ok_or_else(|| anyhow::anyhow!("no config found at {}", config_path.display()))?;
+
+    let (report, failed) = render_validation_report(&config, &repo_root);
+    print!("{report}");
+    if failed > 0 {
+        anyhow::bail!(
+            "{failed} of {} presets have problems in {}",
+            config.presets.len(),
+            config_path.display()
+        );
+    }
+    match &config.schema {
+        Some(schema) => println!(
+            "config OK ({} presets, schema {})",
+            config.presets.len(),
+            schema
+        ),
+        None => println!("config OK ({} presets)", config.presets.len()),
+    }
+    Ok(())
+}
+
+/// One OK/error line per preset (with its problems indented below), and how many failed.
+fn render_validation_report(config: &ConfigFile, repo_root: &Path) -> (String, usize) {
+    let mut output = String::new();
+    let mut failed = 0;
+    for (name, preset) in &config.presets {
+        let problems = gather::preset_problems(preset, repo_root);
+        if problems.is_empty() {
+            output.push_str(&format!("ok     {name}\n"));
+            continue;
+        }
+        failed += 1;
+        output.push_str(&format!("error  {name}\n"));
+        for problem in problems {
+            output.push_str(&format!("         - {problem}\n"));
+        }
+    }
+    (output, failed)
+}
+
+fn run_list(
+    config: Option<&str>,
+    no_global: bool,
+    root_markers: &[String],
+    painter: Painter,
+) -> Result<()> {
+    let (repo_root, config_path) = locate_project(root_markers, config)?;
+    match load_config(&config_path, no_global)? {
+        Some(config) => print!("{}", render_preset_list(&config, &repo_root, painter)),
+        None => println!("No config found at {}", config_path.display()),
+    }
+    Ok(())
+}
+
+fn run_show(config: Option<&str>, no_global: bool, root_markers: &[String]) -> Result<()> {
+    let (repo_root, config_path) = locate_project(root_markers, config)?;
+    match load_config(&config_path, no_global)? {
+        Some(config) => print!("{}", render_resolved_config(&config, &repo_root)?),
+        None => println!("No config found at {}", config_path.display()),
+    }
+    Ok(())
+}
+
+/// Normalized YAML of the effective config: presets after `extends` with absolute bases,
+/// and the test patterns, clipboard order, and ignored directories actually in force.
+fn render_resolved_config(config: &ConfigFile, repo_root: &Path) -> Result<String> {
+    let presets: serde_json::Map<String, serde_json::Value> = config
+        .presets
+        .iter()
+        .map(|(name, preset)| {
+            let mut resolved = serde_json::json!({
+                "base": resolve_base(preset, repo_root),
+                "include": preset.include,
+                "exclude": preset.exclude,
+                "enabled": preset.enabled,
+            });
+            if let Some(format) = preset.format {
+                resolved["format"] = serde_json::json!(format);
+            }
+            if let Some(from_file) = &preset.from_file {
+                resolved["from_file"] = serde_json::json!(repo_root.join(from_file));
+            }
+            if !preset.presets.is_empty() {
+                resolved = serde_json::json!({
+                    "presets": preset.presets,
+                    "enabled": preset.enabled,
+                });
+            }
+            (name.clone(), resolved)
+        })
+        .collect();
+    let test_patterns = config.test_patterns.clone().unwrap_or_else(|| {
+        DEFAULT_TEST_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect()
+    });
+    let clipboard_order = config
+        .clipboard_order
+        .clone()
+        .unwrap_or_else(|| clipboard::backend_names().map(String::from).collect());
+
+    let mut resolved = serde_json::json!({
+        "version": config.version,
+        "presets": presets,
+        "exclude": config.exclude,
+        "test_patterns": test_patterns,
+        "clipboard_order": clipboard_order,
+        "ignore_dirs": config.ignored_dirs(),
+        "content_filters": config.content_filters,
+        "promote_readme": config.promote_readme.unwrap_or(true),
+        "keep_comments": config.keep_comments,
+        "template": config.template.clone().unwrap_or_default(),
+    });
+    if let Some(schema) = &config.schema {
+        resolved["schema"] = schema.clone().into();
+    }
+    if let Some(default) = &config.default {
+        resolved["default"] = default.clone().into();
+    }
+    serde_yaml::to_string(&resolved).context("failed to serialize resolved config")
+}
+
+/// Describe each enabled preset: its name, base (with the resolved path), and patterns.
+fn render_preset_list(config: &ConfigFile, repo_root: &Path, painter: Painter) -> String {
+    let mut output = String::new();
+    for (name, preset) in config.enabled_presets() {
+        if !preset.presets.is_empty() {
+            output.push_str(&format!("{}\n", painter.green(name)));
+            output.push_str(&format!("  presets: {}\n", preset.presets.join(", ")));
+            continue;
+        }
+        let base = preset
+            .base
+            .as_deref()
+            .map_or_else(|| ".".to_string(), |base| base.display().to_string());
+        output.push_str(&format!("{}\n", painter.green(name)));
+        output.push_str(&format!(
+            "  base:    {base} {}\n",
+            painter.dim(&format!("({})", resolve_base(preset, repo_root).display()))
+        ));
+        if let Some(from_file) = &preset.from_file {
+            output.push_str(&format!("  from:    {}\n", from_file.display()));
+        }
+        if !preset.include.is_empty() {
+            output.push_str(&format!("  include: {}\n", preset.include.join(", ")));
+        }
+        if !preset.exclude.is_empty() {
+            output.push_str(&format!("  exclude: {}\n", preset.exclude.join(", ")));
+        }
+    }
+
+    if output.is_empty() {
+        output.push_str("No presets defined.\n");
+    }
+    output
+}
+
 fn run_init() -> Result<()> {
     let config_path = Path::new(CONFIG_FILE_NAME);
 
@@ -285,7 +1799,7 @@ fn run_install_script() -> Result<()> {
 /// Config template for `gf init`.
 const CONFIG_TEMPLATE: &str = r#"# gather-files configuration
 # Docs: https://github.com/BrianSigafoos/gather-files
-version: 1
+version: 2
 
 presets:
   # Example: gather all source code
@@ -301,6 +1815,7 @@ presets:
   # Example: gather documentation
   # docs:
   #   base: docs
+  #   format: markdown
   #   include:
   #     - "**/*.md"
 
@@ -338,6 +1853,409 @@ mod tests {
         assert!(!is_newer_version("1.0.0", "1.0.1"));
     }
 
+    #[test]
+    fn prompt_for_preset_accepts_number_or_name() {
+        let names = ["src", "docs"];
+        let mut output = Vec::new();
+
+        let choice = prompt_for_preset(&names, &mut "2\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(choice, "docs");
+        assert!(String::from_utf8(output).unwrap().contains("  1) src"));
+
+        let choice = prompt_for_preset(&names, &mut "src\n".as_bytes(), &mut Vec::new()).unwrap();
+        assert_eq!(choice, "src");
+
+        assert!(prompt_for_preset(&names, &mut "9\n".as_bytes(), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn choose_destination_follows_output_flags() {
+        let root = Path::new("/repo");
+        let destination = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["gf"].iter().chain(args)).unwrap();
+            match choose_destination(&cli, root, None) {
+                Destination::Clipboard(_) => "clipboard".to_string(),
+                Destination::ClipboardBackend(name) => format!("clipboard {name}"),
+                Destination::Stdout => "stdout".to_string(),
+                Destination::Discard => "discard".to_string(),
+                Destination::File(path) => format!("file {}", path.display()),
+                Destination::AppendFile(path) => format!("append {}", path.display()),
+            }
+        };
+        assert_eq!(destination(&[]), "clipboard");
+        assert_eq!(destination(&["--clipboard", "xclip"]), "clipboard xclip");
+        assert_eq!(destination(&["--stdout"]), "stdout");
+        assert_eq!(destination(&["--no-clipboard"]), "discard");
+        assert_eq!(destination(&["-o", "out.txt"]), "file /repo/out.txt");
+        assert_eq!(
+            destination(&["-o", "out.txt", "--append"]),
+            "append /repo/out.txt"
+        );
+        assert_eq!(
+            destination(&["-o", "out.txt", "--chunk-size", "10"]),
+            "clipboard"
+        );
+    }
+
+    #[test]
+    fn render_stats_sorts_largest_first_with_percentages() {
+        let section = |display: &str, chars: usize| SectionSize {
+            display: display.to_string(),
+            chars,
+            skipped: false,
+        };
+        let table = render_stats(&[section("README.md", 250), section("src/main.rs", 750)]);
+        assert_eq!(
+            table,
+            "chars       %  path\n  750   75.0%  src/main.rs\n  250   25.0%  README.md\n 1000  100.0%  total\n"
+        );
+    }
+
+    #[test]
+    fn stats_json_reports_totals_and_files_in_order() {
+        let section = |display: &str, chars: usize| SectionSize {
+            display: display.to_string(),
+            chars,
+            skipped: false,
+        };
+        let stats = render_stats_json(
+            &[section("src/main.rs", 30), section("README.md", 70)],
+            120,
+            Some(25),
+            Duration::from_millis(1500),
+        );
+        assert_eq!(
+            stats,
+            serde_json::json!({
+                "files": 2,
+                "chars": 120,
+                "tokens": 25,
+                "elapsed_ms": 1500.0,
+                "per_file": [
+                    { "path": "src/main.rs", "chars": 30 },
+                    { "path": "README.md", "chars": 70 }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn render_profile_lists_phases_with_file_count() {
+        let table = render_profile(
+            &[
+                ("collect", Duration::from_micros(12_340)),
+                ("copy", Duration::from_millis(3)),
+            ],
+            42,
+        );
+        assert_eq!(
+            table,
+            "profile: collect      12.34ms  (42 files)\nprofile: copy          3.00ms\n"
+        );
+    }
+
+    #[test]
+    fn render_preview_shows_head_and_totals() {
+        let preview = render_preview("one\ntwo\nthree\n", 2, 14);
+        assert_eq!(
+            preview,
+            "--- preview: first 2 of 3 lines (14 chars total) ---\none\ntwo\n--- end preview ---\n"
+        );
+    }
+
+    #[test]
+    fn find_repo_root_stops_at_builtin_and_custom_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("services/api/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_repo_root(&nested, &[]), None);
+
+        std::fs::write(
+            root.join("package.json"),
+            r#"{"workspaces": ["services/*"]}"#,
+        )
+        .unwrap();
+        std::fs::write(root.join("services/api/package.json"), r#"{"name": "api"}"#).unwrap();
+        assert_eq!(find_repo_root(&nested, &[]), Some(root.to_path_buf()));
+
+        std::fs::write(root.join("services/api/WORKSPACE"), "").unwrap();
+        let markers = vec!["WORKSPACE".to_string()];
+        assert_eq!(
+            find_repo_root(&nested, &markers),
+            Some(root.join("services/api"))
+        );
+    }
+
+    #[test]
+    fn cli_excludes_are_kept_apart_from_config_excludes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: ConfigFile =
+            serde_yaml::from_str("version: 2\nexclude: [\"**/*.lock\"]\npresets: {}\n").unwrap();
+        let cli = Cli::try_parse_from(["gf", "--exclude", "docs/**", "--exclude", "*.md"]).unwrap();
+        let options = walk_options(&cli, dir.path(), Some(&config)).unwrap();
+        assert_eq!(options.exclude, ["**/*.lock"]);
+        assert_eq!(options.cli_exclude, ["docs/**", "*.md"]);
+
+        assert!(Cli::try_parse_from(["gf", "--exclude", "src/[a"]).is_err());
+    }
+
+    #[test]
+    fn include_globs_gather_from_the_target_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in ["README.md", "app/main.rs", "app/gen/out.rs", "app/notes.md"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+
+        let cli = Cli::try_parse_from(["gf", "app", "--include", "**/*.rs", "--exclude", "gen/**"])
+            .unwrap();
+        let target = include_target(&cli, root, None, &mut Warnings::default()).unwrap();
+        assert_eq!(target.files, vec![root.join("app/main.rs")]);
+
+        let cli = Cli::try_parse_from([
+            "gf",
+            "--include",
+            "*.rs",
+            "--base",
+            "app",
+            "--exclude",
+            "gen/**",
+        ])
+        .unwrap();
+        let target = include_target(&cli, root, None, &mut Warnings::default()).unwrap();
+        assert_eq!(target.files, vec![root.join("app/main.rs")]);
+
+        let cli = Cli::try_parse_from(["gf", "app/main.rs", "--include", "*.rs"]).unwrap();
+        assert!(include_target(&cli, root, None, &mut Warnings::default()).is_err());
+        let cli =
+            Cli::try_parse_from(["gf", "--include", "*.rs", "--base", "app/main.rs"]).unwrap();
+        assert!(include_target(&cli, root, None, &mut Warnings::default()).is_err());
+    }
+
+    #[test]
+    fn config_search_report_shows_each_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_config = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&repo_config, "version: 2\n").unwrap();
+        let global = dir.path().join("global.yaml");
+
+        let report = config_search_report(&repo_config, Some(&global), false);
+        assert_eq!(
+            report,
+            [
+                format!("config {} (loaded)", repo_config.display()),
+                format!("global config {} (not found)", global.display()),
+            ]
+        );
+        let report = config_search_report(&repo_config, None, true);
+        assert_eq!(report[1], "global config skipped (--no-global)");
+    }
+
+    #[test]
+    fn count_summary_adds_only_requested_counts() {
+        let counts = TextCounts {
+            words: 12,
+            lines: 4,
+        };
+        let cli = Cli::try_parse_from(["gf", "docs"]).unwrap();
+        assert_eq!(
+            count_summary(80, None, requested_counts(&cli, counts)),
+            "80 chars"
+        );
+        let cli = Cli::try_parse_from(["gf", "docs", "--count-words", "--count-lines"]).unwrap();
+        assert_eq!(
+            count_summary(80, Some(20), requested_counts(&cli, counts)),
+            "80 chars, 12 words, 4 lines (~20 tokens)"
+        );
+    }
+
+    #[test]
+    fn quiet_moves_status_lines_to_stderr() {
+        let cli = Cli::try_parse_from(["gf", "src"]).unwrap();
+        assert!(!status_on_stderr(&cli));
+        let cli = Cli::try_parse_from(["gf", "src", "-q", "-o", "bundle.txt"]).unwrap();
+        assert!(cli.quiet && status_on_stderr(&cli));
+        let cli = Cli::try_parse_from(["gf", "src", "--stdout"]).unwrap();
+        assert!(status_on_stderr(&cli));
+    }
+
+    #[test]
+    fn no_clipboard_discards_bundle_and_rejects_other_destinations() {
+        assert!(Cli::try_parse_from(["gf", "src", "--no-clipboard"]).is_ok());
+        for other in [
+            ["--stdout", ""],
+            ["-o", "bundle.txt"],
+            ["--clipboard", "osc52"],
+        ] {
+            let args = ["gf", "src", "--no-clipboard", other[0], other[1]];
+            let args = args.iter().filter(|arg| !arg.is_empty());
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+        Destination::Discard
+            .deliver("bundle", &mut Warnings::default())
+            .unwrap();
+    }
+
+    #[test]
+    fn display_root_defaults_to_repo_root_and_resolves_relative_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("services/api")).unwrap();
+
+        assert_eq!(display_root(None, root).unwrap(), root);
+        let api = display_root(Some("services/api"), root).unwrap();
+        assert_eq!(api, root.join("services/api"));
+        assert_eq!(display_path(&api.join("src/main.rs"), &api), "src/main.rs");
+        let outside = root.join("README.md");
+        assert_eq!(display_path(&outside, &api), outside.display().to_string());
+
+        assert!(display_root(Some("services/missing"), root).is_err());
+    }
+
+    #[test]
+    fn render_preset_list_shows_patterns_and_resolved_base() {
+        let config: ConfigFile = serde_yaml::from_str(
+            "version: 1\npresets:\n  web:\n    base: app\n    include: [\"**/*.ts\", \"*.json\"]\n    exclude: [\"dist/**\"]\n  old:\n    include: [\"*\"]\n    enabled: false\n",
+        )
+        .unwrap();
+        let listing = render_preset_list(
+            &config,
+            Path::new("/repo"),
+            Painter::stdout(ColorChoice::Never),
+        );
+        assert_eq!(
+            listing,
+            "web\n  base:    app (/repo/app)\n  include: **/*.ts, *.json\n  exclude: dist/**\n"
+        );
+    }
+
+    #[test]
+    fn find_config_upward_returns_nearest_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("packages/web/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(CONFIG_FILE_NAME), "version: 1\n").unwrap();
+        assert_eq!(
+            find_config_upward(&nested),
+            Some(root.join(CONFIG_FILE_NAME))
+        );
+
+        std::fs::write(
+            root.join("packages/web").join(CONFIG_FILE_NAME),
+            "version: 1\n",
+        )
+        .unwrap();
+        assert_eq!(
+            find_config_upward(&nested),
+            Some(root.join("packages/web").join(CONFIG_FILE_NAME))
+        );
+    }
+
+    #[test]
+    fn project_paths_anchor_root_to_found_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let proj = dir.path().join("proj");
+        let sub = proj.join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(proj.join(CONFIG_FILE_NAME), "version: 1\n").unwrap();
+
+        // No root marker: the config's directory is the root, not the current directory.
+        let (root, config_path) = project_paths(&sub, &[], None);
+        assert_eq!(root, proj);
+        assert_eq!(config_path, proj.join(CONFIG_FILE_NAME));
+
+        // A config in a subproject of a repo anchors to that subproject.
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(project_paths(&sub, &[], None).0, proj);
+
+        // A config above the repo root leaves the repo root in charge.
+        let markers = vec!["WORKSPACE".to_string()];
+        std::fs::write(sub.join("WORKSPACE"), "").unwrap();
+        let (root, config_path) = project_paths(&sub, &markers, None);
+        assert_eq!(root, sub);
+        assert_eq!(config_path, proj.join(CONFIG_FILE_NAME));
+
+        let (root, config_path) = project_paths(&sub, &[], Some("custom.yaml"));
+        assert_eq!(
+            (root, config_path),
+            (dir.path().to_path_buf(), dir.path().join("custom.yaml"))
+        );
+    }
+
+    #[test]
+    fn validation_report_checks_bases_and_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        let config: ConfigFile = serde_yaml::from_str(
+            "version: 2\npresets:\n  web:\n    base: app\n    include: [\"**/*.ts\"]\n  api:\n    base: services/api\n    from_file: lists/api.txt\n  all:\n    presets: [web, api]\n",
+        )
+        .unwrap();
+
+        let (report, failed) = render_validation_report(&config, root);
+        assert_eq!(failed, 1);
+        assert_eq!(
+            report,
+            format!(
+                "ok     web\nerror  api\n         - base {} is not a directory\n         - from_file {} does not exist\nok     all\n",
+                root.join("services/api").display(),
+                root.join("lists/api.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn render_resolved_config_shows_effective_values() {
+        let config: ConfigFile = serde_yaml::from_str(
+            "version: 1\nignore_dirs: [dist]\npresets:\n  web:\n    base: app\n    include: [\"**/*.ts\"]\n    format: markdown\n",
+        )
+        .unwrap();
+        let yaml = render_resolved_config(&config, Path::new("/repo")).unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(value["presets"]["web"]["base"], "/repo/app");
+        assert_eq!(value["presets"]["web"]["format"], "markdown");
+        assert_eq!(value["ignore_dirs"][3], "dist");
+        assert_eq!(value["test_patterns"][0], DEFAULT_TEST_PATTERNS[0]);
+        assert_eq!(value["clipboard_order"][0], "pbcopy");
+        assert_eq!(value["template"]["header"], "# {path}");
+    }
+
+    #[test]
+    fn completions_cover_subcommands_and_flags() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "gf", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("validate"));
+        assert!(script.contains("--no-gitignore"));
+    }
+
+    #[test]
+    fn parse_size_accepts_human_readable_suffixes() {
+        assert_eq!(parse_size("1500").unwrap(), 1500);
+        assert_eq!(parse_size("500KB").unwrap(), 500 * 1024);
+        assert_eq!(parse_size("1.5mb").unwrap(), 1536 * 1024);
+        assert_eq!(parse_size("2 GB").unwrap(), 2 << 30);
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
+    #[test]
+    fn parse_content_filter_splits_extension_and_command() {
+        assert_eq!(
+            parse_content_filter(".ipynb=jupyter nbconvert --to script").unwrap(),
+            (
+                "ipynb".to_string(),
+                "jupyter nbconvert --to script".to_string()
+            )
+        );
+        assert!(parse_content_filter("ipynb").is_err());
+        assert!(parse_content_filter("=cat").is_err());
+    }
+
     #[test]
     fn is_newer_version_handles_double_digit_versions() {
         assert!(is_newer_version("0.1.23", "0.1.22"));
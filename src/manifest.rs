@@ -0,0 +1,86 @@
+use crate::gather::display_path;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Read a manifest (one repo-relative path per line) and resolve it against `repo_root`.
+pub fn read_manifest(path: &Path, repo_root: &Path) -> Result<Vec<PathBuf>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read manifest {}", path.display()))?;
+    parse_file_list(&contents, repo_root)
+}
+
+/// Parse a newline-delimited file list, skipping blank lines and `#` comments.
+pub fn parse_file_list(contents: &str, repo_root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let path = Path::new(line);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            repo_root.join(path)
+        };
+
+        if !path.is_file() {
+            anyhow::bail!("file '{line}' does not exist");
+        }
+        files.push(path);
+    }
+
+    Ok(files)
+}
+
+/// Write the ordered list of gathered files as a manifest that `--manifest` can replay.
+pub fn write_manifest(path: &Path, files: &[PathBuf], repo_root: &Path) -> Result<()> {
+    let mut contents = String::new();
+    for file in files {
+        contents.push_str(&display_path(file, repo_root));
+        contents.push('\n');
+    }
+
+    fs::write(path, contents)
+        .with_context(|| format!("failed to write manifest {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn manifest_round_trips_in_order() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "main").unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+
+        let files = vec![root.join("src/main.rs"), root.join("README.md")];
+        let manifest = root.join("gathered.txt");
+        write_manifest(&manifest, &files, root).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "src/main.rs\nREADME.md\n"
+        );
+        assert_eq!(read_manifest(&manifest, root).unwrap(), files);
+    }
+
+    #[test]
+    fn parse_file_list_skips_comments_and_reports_missing_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.txt"), "a").unwrap();
+
+        let files = parse_file_list("# notes\n\na.txt\n", root).unwrap();
+        assert_eq!(files, vec![root.join("a.txt")]);
+
+        let err = parse_file_list("missing.txt\n", root).unwrap_err();
+        assert!(err.to_string().contains("missing.txt"));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::warnings::Warnings;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Read the timestamp recorded by the previous `--since-file` run.
+///
+/// A missing marker is `Ok(None)`, meaning this is the first run and everything is gathered.
+pub fn read_marker(path: &Path) -> Result<Option<SystemTime>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read marker {}", path.display()));
+        }
+    };
+
+    let millis: u64 = contents.trim().parse().with_context(|| {
+        format!(
+            "marker {} does not hold a timestamp (milliseconds since the Unix epoch)",
+            path.display()
+        )
+    })?;
+    Ok(Some(UNIX_EPOCH + Duration::from_millis(millis)))
+}
+
+/// Record `time` in the marker file for the next run.
+pub fn write_marker(path: &Path, time: SystemTime) -> Result<()> {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before the Unix epoch")?
+        .as_millis();
+    fs::write(path, format!("{millis}\n"))
+        .with_context(|| format!("failed to write marker {}", path.display()))
+}
+
+/// Parse a `--modified-within` window such as `90s`, `30m`, `2h`, `3d`, or `1w`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{value}' (use s, m, h, d, or w)"))?;
+    let (number, unit) = trimmed.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}'"))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit in '{value}' (use s, m, h, d, or w)"
+            ));
+        }
+    };
+    let seconds = number
+        .checked_mul(seconds)
+        .ok_or_else(|| format!("duration '{value}' is too large"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// The start of a `--modified-within` window ending at `now`.
+pub fn window_start(now: SystemTime, window: Duration) -> Result<SystemTime> {
+    now.checked_sub(window)
+        .context("--modified-within reaches back further than the system clock can represent")
+}
+
+/// Keep only files modified after `since`; files that can't be stat'd are skipped with a warning.
+pub fn modified_after(
+    files: Vec<PathBuf>,
+    since: SystemTime,
+    warnings: &mut Warnings,
+) -> Vec<PathBuf> {
+    let mut kept = Vec::with_capacity(files.len());
+    for file in files {
+        match fs::metadata(&file).and_then(|metadata| metadata.modified()) {
+            Ok(modified) if modified > since => kept.push(file),
+            Ok(_) => {}
+            Err(err) => warnings.push(format!(
+                "skipped {}: can't read modification time: {err}",
+                file.display()
+            )),
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn marker_round_trips_and_filters_older_files() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join(".gf-since");
+        assert!(read_marker(&marker).unwrap().is_none());
+
+        let old = dir.path().join("old.txt");
+        let new = dir.path().join("new.txt");
+        fs::write(&old, "old").unwrap();
+        fs::write(&new, "new").unwrap();
+        let checkpoint = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(checkpoint - Duration::from_secs(60))
+            .unwrap();
+
+        write_marker(&marker, checkpoint).unwrap();
+        let since = read_marker(&marker).unwrap().unwrap();
+        let mut warnings = Warnings::default();
+        let missing = dir.path().join("missing.txt");
+        assert_eq!(
+            modified_after(vec![old, new.clone(), missing], since, &mut warnings),
+            vec![new]
+        );
+        assert_eq!(warnings.messages().len(), 1);
+
+        fs::write(&marker, "yesterday").unwrap();
+        assert!(read_marker(&marker).is_err());
+    }
+
+    #[test]
+    fn parse_duration_accepts_simple_suffixes() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(
+            parse_duration("3d").unwrap(),
+            Duration::from_secs(3 * 86400)
+        );
+        assert_eq!(parse_duration("1w").unwrap(), Duration::from_secs(604800));
+        assert!(parse_duration("2").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("2y").is_err());
+        assert_eq!(
+            parse_duration("30000000000000000w").unwrap_err(),
+            "duration '30000000000000000w' is too large"
+        );
+    }
+
+    #[test]
+    fn window_start_rejects_windows_before_the_clock_can_represent() {
+        let now = SystemTime::now();
+        assert_eq!(
+            window_start(now, Duration::from_secs(60)).unwrap(),
+            now - Duration::from_secs(60)
+        );
+        assert!(window_start(now, Duration::from_secs(u64::MAX)).is_err());
+    }
+}
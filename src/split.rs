@@ -0,0 +1,328 @@
+use crate::gather::{OutputFormat, RenderOptions, Section, SectionSize, render_sections};
+use crate::tokens::{self, TextCounts};
+use crate::warnings::Warnings;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A written part file and its size in bytes.
+pub struct Part {
+    pub path: PathBuf,
+    pub bytes: usize,
+}
+
+/// Pack sections into parts of at most `limit` bytes, never splitting a section.
+///
+/// A section larger than `limit` on its own is placed alone in an oversized part.
+pub fn pack_sections(sections: &[Section], limit: usize) -> Vec<String> {
+    pack_by(sections, limit, |section| section.text.len())
+}
+
+/// Pack sections greedily, measuring each with `size`, never splitting a section.
+fn pack_by(sections: &[Section], limit: usize, size: impl Fn(&Section) -> usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut current_size = 0;
+
+    for section in sections {
+        let section_size = size(section);
+        if !current.is_empty() && current_size + section_size > limit {
+            parts.push(std::mem::take(&mut current));
+            current_size = 0;
+        }
+        current.push_str(&section.text);
+        current_size += section_size;
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Write parts as `part-001.txt`, `part-002.txt`, ... into `dir`, creating it if needed.
+pub fn write_parts(dir: &Path, parts: &[String]) -> Result<Vec<Part>> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut written = Vec::with_capacity(parts.len());
+    for (idx, contents) in parts.iter().enumerate() {
+        let path = dir.join(format!("part-{:03}.txt", idx + 1));
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        written.push(Part {
+            path,
+            bytes: contents.len(),
+        });
+    }
+
+    Ok(written)
+}
+
+/// Unit that `--chunk-size` is measured in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChunkUnit {
+    /// Characters, as reported in the summary.
+    #[default]
+    Chars,
+    /// Estimated tokens (~4 characters per token).
+    Tokens,
+}
+
+impl ChunkUnit {
+    fn measure(self, text: &str) -> usize {
+        match self {
+            ChunkUnit::Chars => text.chars().count(),
+            ChunkUnit::Tokens => tokens::estimate_tokens(text),
+        }
+    }
+}
+
+/// Pack sections into chunks of at most `limit` units, each opening with `# Part N of M`.
+///
+/// Room for the header is reserved within `limit`; a section too large on its own still
+/// gets a chunk to itself.
+pub fn pack_chunks(sections: &[Section], limit: usize, unit: ChunkUnit) -> Vec<String> {
+    let reserve = unit.measure(&chunk_header(999, 999));
+    let bodies = pack_by(sections, limit.saturating_sub(reserve), |section| {
+        unit.measure(&section.text)
+    });
+    let total = bodies.len();
+    bodies
+        .into_iter()
+        .enumerate()
+        .map(|(idx, body)| format!("{}{body}", chunk_header(idx + 1, total)))
+        .collect()
+}
+
+fn chunk_header(part: usize, total: usize) -> String {
+    format!("# Part {part} of {total}\n\n")
+}
+
+/// Write chunks next to `base`, numbered into its name: `bundle.txt` becomes
+/// `bundle.001.txt`, `bundle.002.txt`, ...
+pub fn write_chunks(base: &Path, chunks: &[String]) -> Result<Vec<Part>> {
+    if let Some(parent) = base.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let stem = base
+        .file_stem()
+        .map_or_else(|| "bundle".into(), |stem| stem.to_string_lossy());
+    let mut written = Vec::with_capacity(chunks.len());
+    for (idx, contents) in chunks.iter().enumerate() {
+        let name = match base.extension() {
+            Some(extension) => format!("{stem}.{:03}.{}", idx + 1, extension.to_string_lossy()),
+            None => format!("{stem}.{:03}", idx + 1),
+        };
+        let path = base.with_file_name(name);
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        written.push(Part {
+            path,
+            bytes: contents.len(),
+        });
+    }
+
+    Ok(written)
+}
+
+/// How a bundle too large for one paste is laid out on disk.
+pub enum Layout {
+    /// `--split-output`: `part-001.txt`, ... in a directory, each at most `limit` bytes.
+    Dir(PathBuf),
+    /// `--chunk-size`: numbered into a base name, each at most `limit` units including
+    /// its `# Part N of M` header.
+    Chunks { base: PathBuf, unit: ChunkUnit },
+}
+
+impl Layout {
+    /// The flag that chose this layout, for error messages.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Layout::Dir(_) => "--split-output",
+            Layout::Chunks { .. } => "--chunk-size",
+        }
+    }
+
+    /// Pack sections into the contents of each file.
+    pub fn pack(&self, sections: &[Section], limit: usize) -> Vec<String> {
+        match self {
+            Layout::Dir(_) => pack_sections(sections, limit),
+            Layout::Chunks { unit, .. } => pack_chunks(sections, limit, *unit),
+        }
+    }
+
+    /// Write packed contents to their numbered files.
+    pub fn write(&self, contents: &[String]) -> Result<Vec<Part>> {
+        match self {
+            Layout::Dir(dir) => write_parts(dir, contents),
+            Layout::Chunks { base, .. } => write_chunks(base, contents),
+        }
+    }
+}
+
+/// Files written for `--split-output` or `--chunk-size`, with totals for the summary.
+pub struct WrittenParts {
+    pub parts: Vec<Part>,
+    pub char_count: usize,
+    pub token_count: usize,
+    pub text_counts: TextCounts,
+    pub sections: Vec<SectionSize>,
+}
+
+/// Render `files` and write their sections into numbered files laid out by `layout`;
+/// totals include any part headers.
+pub fn write_bundle(
+    files: &[PathBuf],
+    root: &Path,
+    options: &RenderOptions,
+    limit: usize,
+    layout: &Layout,
+    warnings: &mut Warnings,
+) -> Result<WrittenParts> {
+    if options.format == OutputFormat::Json {
+        anyhow::bail!("{} does not support --format json", layout.flag());
+    }
+
+    let sections = render_sections(files, root, options, warnings)?;
+    let contents = layout.pack(&sections, limit);
+    let char_count = contents.iter().map(|part| part.chars().count()).sum();
+    let token_count = contents
+        .iter()
+        .map(|part| tokens::estimate_tokens(part))
+        .sum();
+    let text_counts = contents.iter().map(|part| tokens::count_text(part)).sum();
+    let parts = layout.write(&contents)?;
+    Ok(WrittenParts {
+        parts,
+        char_count,
+        token_count,
+        text_counts,
+        sections: sections.iter().map(Section::size).collect(),
+    })
+}
+
+/// Build the index listing each part, suitable for copying to the clipboard.
+pub fn render_index(parts: &[Part]) -> String {
+    let mut index = format!("Gathered output split into {} parts:\n", parts.len());
+    for part in parts {
+        index.push_str(&format!("{} ({} bytes)\n", part.path.display(), part.bytes));
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn section(text: &str) -> Section {
+        Section {
+            display: String::new(),
+            text: text.to_string(),
+            chars: text.chars().count(),
+            skipped: false,
+        }
+    }
+
+    #[test]
+    fn packs_on_section_boundaries() {
+        let sections = [
+            section("aaaa"),
+            section("bbbb"),
+            section("cc"),
+            section("dddddddd"),
+        ];
+        let parts = pack_sections(&sections, 6);
+        assert_eq!(parts, vec!["aaaa", "bbbbcc", "dddddddd"]);
+    }
+
+    #[test]
+    fn chunks_carry_part_headers_within_limit() {
+        let sections = [
+            section(&"a".repeat(10)),
+            section(&"b".repeat(10)),
+            section("c"),
+        ];
+        let header = chunk_header(999, 999).chars().count();
+        let chunks = pack_chunks(&sections, header + 12, ChunkUnit::Chars);
+        assert_eq!(
+            chunks,
+            vec![
+                format!("# Part 1 of 2\n\n{}", "a".repeat(10)),
+                format!("# Part 2 of 2\n\n{}c", "b".repeat(10)),
+            ]
+        );
+
+        let dir = tempdir().unwrap();
+        let parts = write_chunks(&dir.path().join("out/bundle.txt"), &chunks).unwrap();
+        assert_eq!(parts[1].path, dir.path().join("out/bundle.002.txt"));
+        assert!(
+            fs::read_to_string(&parts[0].path)
+                .unwrap()
+                .starts_with("# Part 1 of 2")
+        );
+    }
+
+    #[test]
+    fn write_bundle_uses_the_chosen_layout() {
+        let dir = tempdir().unwrap();
+        let files: Vec<PathBuf> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            fs::write(file, "x".repeat(20)).unwrap();
+        }
+        let options = RenderOptions::default();
+        let mut warnings = Warnings::default();
+
+        let parts_dir = Layout::Dir(dir.path().join("parts"));
+        let written =
+            write_bundle(&files, dir.path(), &options, 40, &parts_dir, &mut warnings).unwrap();
+        assert_eq!(written.parts.len(), 2);
+        assert_eq!(written.parts[1].path, dir.path().join("parts/part-002.txt"));
+
+        let chunks = Layout::Chunks {
+            base: dir.path().join("bundle.txt"),
+            unit: ChunkUnit::Chars,
+        };
+        let written =
+            write_bundle(&files, dir.path(), &options, 1000, &chunks, &mut warnings).unwrap();
+        assert_eq!(written.parts[0].path, dir.path().join("bundle.001.txt"));
+        let chunk = fs::read_to_string(&written.parts[0].path).unwrap();
+        assert!(chunk.starts_with("# Part 1 of 1\n\n"));
+        assert_eq!(written.char_count, chunk.chars().count());
+
+        let json = RenderOptions {
+            format: OutputFormat::Json,
+            ..RenderOptions::default()
+        };
+        let err = write_bundle(&files, dir.path(), &json, 1000, &chunks, &mut warnings)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "--chunk-size does not support --format json"
+        );
+    }
+
+    #[test]
+    fn writes_numbered_parts_and_index() {
+        let dir = tempdir().unwrap();
+        let parts = write_parts(dir.path(), &["one".to_string(), "two!".to_string()]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("part-002.txt")).unwrap(),
+            "two!"
+        );
+        let index = render_index(&parts);
+        assert!(index.starts_with("Gathered output split into 2 parts:\n"));
+        assert!(index.contains("part-001.txt (3 bytes)"));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::config::{ConfigFile, Preset};
+use crate::gather::{
+    OutputFormat, WalkOptions, collect_from_path, collect_from_preset, promote_readme,
+};
+use crate::manifest;
+use crate::warnings::Warnings;
+use anyhow::{Context, Result};
+use indexmap::IndexSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Files resolved for a run, with a human-readable description of where they came from.
+pub struct Target {
+    pub files: Vec<PathBuf>,
+    pub description: String,
+    /// Output format requested by the preset, if any.
+    pub format: Option<OutputFormat>,
+}
+
+/// Resolve every target and gather the union, keeping first-seen order.
+///
+/// With no targets, the whole repo is gathered. With several, README promotion runs once
+/// over the combined list and the first preset `format` wins. A `-` target reads a
+/// newline-delimited file list from `file_list` (stdin, for `gf`).
+pub fn determine_target(
+    targets: &[String],
+    repo_root: &Path,
+    config: Option<&ConfigFile>,
+    walk: &WalkOptions,
+    file_list: &mut impl Read,
+    warnings: &mut Warnings,
+) -> Result<Target> {
+    match targets {
+        [] => match config.and_then(|config| config.default.as_deref()) {
+            Some(name) => {
+                let (config, preset) = find_preset(config, name)?;
+                Ok(Target {
+                    files: collect_from_preset(
+                        name,
+                        preset,
+                        &config.presets,
+                        repo_root,
+                        walk,
+                        warnings,
+                    )?,
+                    description: format!("default preset '{name}'"),
+                    format: preset.format,
+                })
+            }
+            None => Ok(Target {
+                files: collect_from_path(repo_root, walk, warnings)?,
+                description: format!("root {}", repo_root.display()),
+                format: None,
+            }),
+        },
+        [argument] => resolve_argument(argument, repo_root, config, walk, file_list, warnings),
+        _ => {
+            let mut files = IndexSet::new();
+            let mut descriptions = Vec::with_capacity(targets.len());
+            let mut format = None;
+            for argument in targets {
+                let target =
+                    resolve_argument(argument, repo_root, config, walk, file_list, warnings)?;
+                files.extend(target.files);
+                descriptions.push(target.description);
+                format = format.or(target.format);
+            }
+
+            let mut files: Vec<PathBuf> = files.into_iter().collect();
+            if walk.promote_readme {
+                promote_readme(repo_root, &mut files);
+            }
+            Ok(Target {
+                files,
+                description: descriptions.join(", "),
+                format,
+            })
+        }
+    }
+}
+
+/// Resolve a single target argument: `-`, an existing path, or a preset name.
+fn resolve_argument(
+    argument: &str,
+    repo_root: &Path,
+    config: Option<&ConfigFile>,
+    walk: &WalkOptions,
+    file_list: &mut impl Read,
+    warnings: &mut Warnings,
+) -> Result<Target> {
+    if argument == "-" {
+        let list = std::io::read_to_string(file_list).context("failed to read stdin")?;
+        return Ok(Target {
+            files: manifest::parse_file_list(&list, repo_root)
+                .context("invalid file list on stdin")?,
+            description: "file list from stdin".to_string(),
+            format: None,
+        });
+    }
+
+    let path_candidate = parse_target_path(argument, repo_root);
+    if path_candidate.exists() {
+        return Ok(Target {
+            files: collect_from_path(&path_candidate, walk, warnings)?,
+            description: format!("path {}", path_candidate.display()),
+            format: None,
+        });
+    }
+
+    let (config, preset) = find_preset(config, argument)?;
+    Ok(Target {
+        files: collect_from_preset(argument, preset, &config.presets, repo_root, walk, warnings)?,
+        description: format!("preset '{argument}'"),
+        format: preset.format,
+    })
+}
+
+/// Look up an enabled preset, returning it with the config it came from (for group members).
+pub fn find_preset<'a>(
+    config: Option<&'a ConfigFile>,
+    name: &str,
+) -> Result<(&'a ConfigFile, &'a Preset)> {
+    let config = config
+        .ok_or_else(|| anyhow::anyhow!("no config found when looking for preset '{name}'"))?;
+    let preset = config
+        .preset(name)
+        .ok_or_else(|| config.missing_preset_error(name))?;
+    if !preset.enabled {
+        anyhow::bail!("preset '{name}' is disabled");
+    }
+    Ok((config, preset))
+}
+
+/// Resolve a command-line path against `repo_root` unless it is already absolute.
+pub fn parse_target_path(argument: &str, repo_root: &Path) -> PathBuf {
+    let path = Path::new(argument);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        repo_root.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gather::display_path;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn find_preset_reports_missing_config_and_preset() {
+        let err = find_preset(None, "src").unwrap_err();
+        assert!(err.to_string().contains("no config found"));
+
+        let config: ConfigFile =
+            serde_yaml::from_str("version: 1\npresets:\n  src:\n    include: [\"*.rs\"]\n")
+                .unwrap();
+        assert!(find_preset(Some(&config), "src").is_ok());
+        let err = find_preset(Some(&config), "docs").unwrap_err();
+        assert_eq!(err.to_string(), "preset 'docs' not found in config");
+        let err = find_preset(Some(&config), "scr").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "preset 'scr' not found in config; did you mean 'src'?"
+        );
+    }
+
+    #[test]
+    fn determine_target_unions_targets_in_order() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for file in ["README.md", "docs/guide.md", "src/lib.rs", "src/main.rs"] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+
+        let targets = ["src", "docs", "src/lib.rs", "-", "README.md"].map(String::from);
+        let target = determine_target(
+            &targets,
+            root,
+            None,
+            &WalkOptions::default(),
+            &mut "docs/guide.md\nREADME.md\n".as_bytes(),
+            &mut Warnings::default(),
+        )
+        .unwrap();
+        let files: Vec<String> = target
+            .files
+            .iter()
+            .map(|path| display_path(path, root))
+            .collect();
+        assert_eq!(
+            files,
+            ["README.md", "src/lib.rs", "src/main.rs", "docs/guide.md"]
+        );
+        assert_eq!(target.description.matches("path ").count(), 4);
+        assert!(target.description.contains("file list from stdin"));
+    }
+}
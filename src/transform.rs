@@ -0,0 +1,688 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Line-ending normalization applied to file contents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LineEndings {
+    /// Leave line endings (and any byte order mark) as they are on disk.
+    #[default]
+    Keep,
+    /// Convert CRLF and lone CR to LF and strip a UTF-8 byte order mark.
+    Lf,
+    /// Convert all line endings to CRLF and strip a UTF-8 byte order mark.
+    Crlf,
+}
+
+/// Normalize line endings, returning `None` when nothing changes.
+///
+/// Normalizing also strips a leading UTF-8 byte order mark.
+pub fn normalize_line_endings(contents: &str, line_endings: LineEndings) -> Option<String> {
+    if line_endings != LineEndings::Keep
+        && let Some(without_bom) = contents.strip_prefix('\u{feff}')
+    {
+        let normalized = normalize_line_endings(without_bom, line_endings);
+        return Some(normalized.unwrap_or_else(|| without_bom.to_string()));
+    }
+
+    let needs_lf = contents.contains('\r');
+    match line_endings {
+        LineEndings::Keep => None,
+        LineEndings::Lf if !needs_lf => None,
+        LineEndings::Lf => Some(to_lf(contents)),
+        LineEndings::Crlf => {
+            let lf = if needs_lf {
+                to_lf(contents)
+            } else {
+                contents.to_string()
+            };
+            Some(lf.replace('\n', "\r\n"))
+        }
+    }
+}
+
+fn to_lf(contents: &str) -> String {
+    contents.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Import syntax for a language: its line comment and the statement prefixes that start an import.
+struct ImportSyntax {
+    comment: &'static str,
+    prefixes: &'static [&'static str],
+    /// Lines besides comments and blanks that may come before the imports.
+    preamble: &'static [&'static str],
+}
+
+const RUST: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["use ", "pub use ", "pub(crate) use ", "extern crate "],
+    preamble: &["#![", "#["],
+};
+
+const PYTHON: ImportSyntax = ImportSyntax {
+    comment: "#",
+    prefixes: &["import ", "from "],
+    preamble: &[],
+};
+
+const JAVASCRIPT: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["import ", "import{", "export * from "],
+    preamble: &["\"use strict\"", "'use strict'"],
+};
+
+const GO: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["import ", "import("],
+    preamble: &["package "],
+};
+
+const JVM: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["import "],
+    preamble: &["package ", "@file:"],
+};
+
+const C_FAMILY: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["#include ", "#include<", "#import "],
+    preamble: &["#pragma "],
+};
+
+const CSHARP: ImportSyntax = ImportSyntax {
+    comment: "//",
+    prefixes: &["using "],
+    preamble: &[],
+};
+
+const RUBY: ImportSyntax = ImportSyntax {
+    comment: "#",
+    prefixes: &[
+        "require ",
+        "require_relative ",
+        "require(",
+        "require_relative(",
+    ],
+    preamble: &[],
+};
+
+fn import_syntax(path: &Path) -> Option<&'static ImportSyntax> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let syntax = match extension.as_str() {
+        "rs" => &RUST,
+        "py" | "pyi" => &PYTHON,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => &JAVASCRIPT,
+        "go" => &GO,
+        "java" | "kt" | "kts" | "scala" | "swift" => &JVM,
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "m" | "mm" => &C_FAMILY,
+        "cs" => &CSHARP,
+        "rb" => &RUBY,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Comment syntax for a language: full-line markers and an optional block delimiter pair.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+};
+
+const HASH_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+};
+
+const DASH_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: None,
+};
+
+const HASKELL_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: Some(("{-", "-}")),
+};
+
+const PHP_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &["//", "#"],
+    block: Some(("/*", "*/")),
+};
+
+const CSS_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: Some(("/*", "*/")),
+};
+
+const MARKUP_COMMENTS: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+};
+
+fn comment_syntax(extension: &str) -> Option<&'static CommentSyntax> {
+    let syntax = match extension {
+        "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "m" | "mm" | "cs" | "java"
+        | "kt" | "kts" | "scala" | "swift" | "go" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx"
+        | "mts" | "cts" | "dart" | "zig" | "proto" | "scss" | "groovy" => &C_COMMENTS,
+        "py" | "pyi" | "rb" | "rake" | "sh" | "bash" | "zsh" | "fish" | "pl" | "pm" | "r"
+        | "ex" | "exs" | "yaml" | "yml" | "toml" | "tf" | "hcl" | "nix" => &HASH_COMMENTS,
+        "sql" | "lua" => &DASH_COMMENTS,
+        "hs" => &HASKELL_COMMENTS,
+        "php" => &PHP_COMMENTS,
+        "css" => &CSS_COMMENTS,
+        "html" | "htm" | "xml" | "svg" | "vue" | "svelte" => &MARKUP_COMMENTS,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Drop comment lines, chosen by file extension, unless the extension is in `keep`.
+///
+/// This is a best-effort, line-based strip: only comments that start a line (after
+/// indentation) are removed, trailing comments after code stay, and markers inside
+/// multi-line strings are not recognized. A leading `#!` line is kept.
+/// Returns `None` when the language is unknown or kept.
+pub fn strip_comments(path: &Path, contents: &str, keep: &[String]) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if keep.iter().any(|kept| {
+        kept.trim_start_matches('.')
+            .eq_ignore_ascii_case(&extension)
+    }) {
+        return None;
+    }
+    let syntax = comment_syntax(&extension)?;
+
+    let mut output = String::with_capacity(contents.len());
+    let mut block_end: Option<&str> = None;
+    for (idx, line) in contents.split_inclusive('\n').enumerate() {
+        if let Some(end) = block_end {
+            if let Some(pos) = line.find(end) {
+                block_end = None;
+                push_remainder(&mut output, "", &line[pos + end.len()..]);
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if idx == 0 && trimmed.starts_with("#!") {
+            output.push_str(line);
+            continue;
+        }
+        if syntax.line.iter().any(|marker| trimmed.starts_with(marker)) {
+            continue;
+        }
+        if let Some((start, end)) = syntax.block
+            && let Some(after) = trimmed.strip_prefix(start)
+        {
+            match after.find(end) {
+                Some(pos) => {
+                    let indent = &line[..line.len() - trimmed.len()];
+                    push_remainder(&mut output, indent, &after[pos + end.len()..]);
+                }
+                None => block_end = Some(end),
+            }
+            continue;
+        }
+        output.push_str(line);
+    }
+    Some(output)
+}
+
+/// Keep code that follows a closing block comment on the same line.
+fn push_remainder(output: &mut String, indent: &str, rest: &str) {
+    if !rest.trim().is_empty() {
+        output.push_str(indent);
+        output.push_str(rest.trim_start());
+    }
+}
+
+/// How many leading bytes are scanned when sniffing for binary content.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// Line width for base64-encoded binary contents.
+const BASE64_LINE_WIDTH: usize = 76;
+
+/// Treat a file as binary when a NUL byte appears in its first 8 KiB, as git does.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// Base64-encode `bytes` in fixed-width lines, each ending in a newline.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    // Every 3 input bytes become 4 output characters, so whole lines need no padding.
+    let line_bytes = BASE64_LINE_WIDTH / 4 * 3;
+    let mut output = String::with_capacity(bytes.len() / 3 * 4 + bytes.len() / line_bytes + 5);
+    for line in bytes.chunks(line_bytes) {
+        BASE64.encode_string(line, &mut output);
+        output.push('\n');
+    }
+    output
+}
+
+/// Pipe `contents` through a shell command and return its stdout.
+///
+/// Filters receive raw bytes, so they can convert binary formats (PDFs, notebooks) to text.
+pub fn run_content_filter(command: &str, contents: &[u8]) -> Result<String> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run content filter '{command}'"))?;
+
+    // Write from a separate thread so a filter that streams output can't deadlock us.
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("content filter stdin unavailable")?;
+    let input = contents.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("content filter '{command}' failed"))?;
+    // A filter may exit without reading all input; its exit status is what matters.
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "content filter '{command}' exited with {}: {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    String::from_utf8(output.stdout)
+        .with_context(|| format!("content filter '{command}' produced invalid UTF-8"))
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+/// Prefix each line with its right-aligned number, e.g. `  12 | let x = 1;`, sized to the
+/// file's line count.
+pub fn number_lines(contents: &str) -> String {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let width = lines.len().to_string().len();
+    let mut output = String::with_capacity(contents.len() + lines.len() * (width + 3));
+    for (idx, line) in lines.iter().enumerate() {
+        output.push_str(&format!("{:>width$} | {line}", idx + 1));
+    }
+    output
+}
+
+/// Which end of a file `--head`/`--tail` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineLimit {
+    Head(usize),
+    Tail(usize),
+}
+
+/// Keep only the first or last lines of `contents`, marking the cut with an annotation
+/// unless `annotate` is false.
+///
+/// Returns `None` when the file already fits.
+pub fn limit_lines(contents: &str, limit: LineLimit, annotate: bool) -> Option<String> {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let (keep, flag) = match limit {
+        LineLimit::Head(keep) => (keep, "--head"),
+        LineLimit::Tail(keep) => (keep, "--tail"),
+    };
+    if lines.len() <= keep {
+        return None;
+    }
+
+    let omitted = lines.len() - keep;
+    let marker = skip_annotation(
+        &format!("{omitted} more lines"),
+        &format!("truncated by {flag} {keep}"),
+    );
+    let mut output = String::new();
+    match limit {
+        LineLimit::Head(_) => {
+            output.push_str(&lines[..keep].concat());
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            if annotate {
+                output.push_str(&marker);
+                output.push('\n');
+            }
+        }
+        LineLimit::Tail(_) => {
+            if annotate {
+                output.push_str(&marker);
+                output.push('\n');
+            }
+            output.push_str(&lines[omitted..].concat());
+        }
+    }
+    Some(output)
+}
+
+/// Uniform marker for content gf left out, e.g. `[gf: skipped 12 imports — import block collapsed]`.
+pub fn skip_annotation(subject: &str, reason: &str) -> String {
+    format!("[gf: skipped {subject} — {reason}]")
+}
+
+/// Collapse the import block at the top of a file into a single annotation line, commented
+/// with the language's own syntax, or drop it when `annotate` is false.
+///
+/// The block must follow only a shebang, comments, docstrings, blank lines, and
+/// language preamble such as attributes or a `package` line, and each import must start
+/// in column 0, so nested imports and prose further down are left alone.
+///
+/// Returns `None` when the language is unknown or the block holds fewer than two imports.
+pub fn trim_imports(path: &Path, contents: &str, annotate: bool) -> Option<String> {
+    let syntax = import_syntax(path)?;
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let start = preamble_end(syntax, &lines);
+    if !lines
+        .get(start)
+        .is_some_and(|line| is_import_start(syntax, line))
+    {
+        return None;
+    }
+
+    let mut end = start;
+    let mut last_import_end = start;
+    let mut imports = 0;
+    while end < lines.len() {
+        let line = lines[end];
+        if line.trim().is_empty() {
+            end += 1;
+            continue;
+        }
+        if !is_import_start(syntax, line) {
+            break;
+        }
+
+        imports += 1;
+        end = statement_end(&lines, end);
+        last_import_end = end;
+    }
+
+    if imports < 2 {
+        return None;
+    }
+
+    let mut output = String::with_capacity(contents.len());
+    for line in &lines[..start] {
+        output.push_str(line);
+    }
+    if annotate {
+        let note = skip_annotation(&format!("{imports} imports"), "import block collapsed");
+        output.push_str(&format!("{} {note}\n", syntax.comment));
+    }
+    for line in &lines[last_import_end..] {
+        output.push_str(line);
+    }
+    Some(output)
+}
+
+fn is_import_start(syntax: &ImportSyntax, line: &str) -> bool {
+    syntax
+        .prefixes
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// Delimiters of comments and docstrings that may span lines before the imports.
+const PREAMBLE_BLOCKS: &[(&str, &str)] = &[("/*", "*/"), ("\"\"\"", "\"\"\""), ("'''", "'''")];
+
+/// Index of the first line that isn't a shebang, comment, docstring, blank, or preamble.
+fn preamble_end(syntax: &ImportSyntax, lines: &[&str]) -> usize {
+    let mut idx = 0;
+    while let Some(line) = lines.get(idx) {
+        let trimmed = line.trim();
+        let block = PREAMBLE_BLOCKS
+            .iter()
+            .find(|(open, _)| trimmed.starts_with(open));
+        if let Some((open, close)) = block {
+            idx = block_end(lines, idx, open, close);
+            continue;
+        }
+
+        let skipped = trimmed.is_empty()
+            || (idx == 0 && trimmed.starts_with("#!"))
+            || trimmed.starts_with(syntax.comment)
+            || syntax
+                .preamble
+                .iter()
+                .any(|prefix| trimmed.starts_with(prefix));
+        if !skipped {
+            break;
+        }
+        idx += 1;
+    }
+    idx
+}
+
+/// Index just past the line closing a block comment or docstring opened on line `start`.
+fn block_end(lines: &[&str], start: usize, open: &str, close: &str) -> usize {
+    let first = lines[start].trim();
+    if first[open.len()..].contains(close) {
+        return start + 1;
+    }
+    lines[start + 1..]
+        .iter()
+        .position(|line| line.contains(close))
+        .map_or(lines.len(), |offset| start + offset + 2)
+}
+
+/// Return the index just past the statement starting at `start`, following
+/// brackets so multi-line imports (`use a::{...};`, `import (...)`) stay whole.
+fn statement_end(lines: &[&str], start: usize) -> usize {
+    let mut depth: i32 = 0;
+    for (idx, line) in lines.iter().enumerate().skip(start) {
+        for ch in line.chars() {
+            match ch {
+                '{' | '(' | '[' => depth += 1,
+                '}' | ')' | ']' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth <= 0 {
+            return idx + 1;
+        }
+    }
+    lines.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_mixed_line_endings() {
+        let mixed = "a\r\nb\nc\rd";
+        assert_eq!(
+            normalize_line_endings(mixed, LineEndings::Lf).unwrap(),
+            "a\nb\nc\nd"
+        );
+        assert_eq!(
+            normalize_line_endings(mixed, LineEndings::Crlf).unwrap(),
+            "a\r\nb\r\nc\r\nd"
+        );
+        assert!(normalize_line_endings(mixed, LineEndings::Keep).is_none());
+        assert!(normalize_line_endings("a\nb\n", LineEndings::Lf).is_none());
+    }
+
+    #[test]
+    fn normalizing_strips_byte_order_mark() {
+        assert_eq!(
+            normalize_line_endings("\u{feff}a\nb\n", LineEndings::Lf).unwrap(),
+            "a\nb\n"
+        );
+        assert_eq!(
+            normalize_line_endings("\u{feff}a\r\n", LineEndings::Crlf).unwrap(),
+            "a\r\n"
+        );
+        assert!(normalize_line_endings("\u{feff}a\n", LineEndings::Keep).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn content_filter_pipes_through_command() {
+        assert_eq!(
+            run_content_filter("tr a-z A-Z", b"hello\n").unwrap(),
+            "HELLO\n"
+        );
+
+        let err = run_content_filter("echo oops >&2; exit 3", b"").unwrap_err();
+        assert!(err.to_string().contains("oops"));
+    }
+
+    #[test]
+    fn detects_binary_and_wraps_base64() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!is_binary("caf\u{e9}\n".as_bytes()));
+
+        let mut late_nul = vec![b'a'; BINARY_SNIFF_LEN];
+        late_nul.push(0);
+        assert!(!is_binary(&late_nul));
+
+        let encoded = encode_base64(&[0u8; 60]);
+        let lines: Vec<&str> = encoded.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), BASE64_LINE_WIDTH);
+        assert!(encoded.ends_with("AAAA\n"));
+
+        let bytes: Vec<u8> = (0..200).collect();
+        let encoded = encode_base64(&bytes);
+        assert_eq!(encoded.replace('\n', ""), BASE64.encode(&bytes));
+        assert!(encoded.lines().all(|line| line.len() <= BASE64_LINE_WIDTH));
+        assert!(encoded.ends_with("=\n"));
+    }
+
+    #[test]
+    fn numbers_lines_with_adaptive_width() {
+        assert_eq!(number_lines("a\nb\n"), "1 | a\n2 | b\n");
+        assert_eq!(number_lines("no newline"), "1 | no newline");
+        assert_eq!(number_lines(""), "");
+
+        let ten = "x\n".repeat(10);
+        let numbered = number_lines(&ten);
+        assert!(numbered.starts_with(" 1 | x\n"));
+        assert!(numbered.ends_with("10 | x\n"));
+    }
+
+    #[test]
+    fn limits_lines_to_head_or_tail_with_marker() {
+        let contents = "one\ntwo\nthree\nfour";
+        assert_eq!(
+            limit_lines(contents, LineLimit::Head(2), true).unwrap(),
+            "one\ntwo\n[gf: skipped 2 more lines — truncated by --head 2]\n"
+        );
+        assert_eq!(
+            limit_lines(contents, LineLimit::Tail(1), true).unwrap(),
+            "[gf: skipped 3 more lines — truncated by --tail 1]\nfour"
+        );
+        assert_eq!(
+            limit_lines(contents, LineLimit::Head(1), false).unwrap(),
+            "one\n"
+        );
+        assert!(limit_lines(contents, LineLimit::Head(4), true).is_none());
+    }
+
+    #[test]
+    fn strips_full_line_and_block_comments() {
+        let source = "//! Crate docs\nuse std::fs;\n\n/* block\n   comment */\n/// Doc\nfn main() { // trailing stays\n    /* inline */ run();\n}\n";
+        assert_eq!(
+            strip_comments(Path::new("main.rs"), source, &[]).unwrap(),
+            "use std::fs;\n\nfn main() { // trailing stays\n    run();\n}\n"
+        );
+
+        let script =
+            "#!/usr/bin/env python\n# setup\nimport os  # keep\n    # indented\nprint(os)\n";
+        assert_eq!(
+            strip_comments(Path::new("run.py"), script, &[]).unwrap(),
+            "#!/usr/bin/env python\nimport os  # keep\nprint(os)\n"
+        );
+
+        assert_eq!(
+            strip_comments(Path::new("q.sql"), "-- note\nSELECT 1;\n", &[]).unwrap(),
+            "SELECT 1;\n"
+        );
+        assert!(strip_comments(Path::new("run.py"), script, &["py".to_string()]).is_none());
+        assert!(strip_comments(Path::new("notes.md"), "# Title\n", &[]).is_none());
+    }
+
+    #[test]
+    fn trims_rust_use_block() {
+        let source = "//! Docs\nuse std::fs;\nuse std::path::{\n    Path,\n    PathBuf,\n};\n\nfn main() {}\n";
+        let trimmed = trim_imports(Path::new("main.rs"), source, true).unwrap();
+        assert_eq!(
+            trimmed,
+            "//! Docs\n// [gf: skipped 2 imports — import block collapsed]\n\nfn main() {}\n"
+        );
+
+        let silent = trim_imports(Path::new("main.rs"), source, false).unwrap();
+        assert_eq!(silent, "//! Docs\n\nfn main() {}\n");
+    }
+
+    #[test]
+    fn trims_python_imports_with_hash_comment() {
+        let source = "import os\nfrom sys import (\n    argv,\n)\n\nprint(argv)\n";
+        let trimmed = trim_imports(Path::new("app.py"), source, true).unwrap();
+        assert_eq!(
+            trimmed,
+            "# [gf: skipped 2 imports — import block collapsed]\n\nprint(argv)\n"
+        );
+    }
+
+    #[test]
+    fn leaves_single_import_and_unknown_languages_untouched() {
+        assert!(trim_imports(Path::new("lib.rs"), "use std::fs;\n\nfn a() {}\n", true).is_none());
+        assert!(trim_imports(Path::new("notes.md"), "import a\nimport b\n", true).is_none());
+    }
+
+    #[test]
+    fn trims_only_the_leading_block_after_preamble() {
+        let source = "#!/usr/bin/env python3\n\"\"\"Tool.\n\nMore docs.\n\"\"\"\n# comment\nimport os\nimport sys\n\nmain()\n";
+        assert_eq!(
+            trim_imports(Path::new("tool.py"), source, false).unwrap(),
+            "#!/usr/bin/env python3\n\"\"\"Tool.\n\nMore docs.\n\"\"\"\n# comment\n\nmain()\n"
+        );
+
+        let attributed =
+            "#![allow(dead_code)]\n/* header\n   use a; */\nuse std::fs;\nuse std::io;\n";
+        assert_eq!(
+            trim_imports(Path::new("lib.rs"), attributed, false).unwrap(),
+            "#![allow(dead_code)]\n/* header\n   use a; */\n"
+        );
+    }
+
+    #[test]
+    fn leaves_nested_and_mid_file_imports_alone() {
+        let nested =
+            "fn a() {}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n    use std::fs;\n}\n";
+        assert!(trim_imports(Path::new("lib.rs"), nested, true).is_none());
+
+        let prose = "\"\"\"Docs.\"\"\"\nprint('hi')\nfrom here on\nimport this\n";
+        assert!(trim_imports(Path::new("app.py"), prose, true).is_none());
+
+        let exports = "export { a } from './a';\nexport { b } from './b';\n";
+        assert!(trim_imports(Path::new("index.ts"), exports, true).is_none());
+    }
+}
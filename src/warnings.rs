@@ -0,0 +1,79 @@
+use crate::color::Painter;
+
+/// Non-fatal problems collected during a run and reported together once it finishes,
+/// plus the `--verbose` notes the caller prints as it goes.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    messages: Vec<String>,
+    notes: Vec<String>,
+}
+
+impl Warnings {
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.messages.push(message.into());
+    }
+
+    /// Record a `--verbose` note, such as how many files a preset pattern matched.
+    pub fn note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    /// Remove and return the notes recorded since the last call, oldest first.
+    pub fn take_notes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notes)
+    }
+
+    /// Append `other`'s messages and notes after this one's, keeping their order.
+    pub fn extend(&mut self, other: Warnings) {
+        self.messages.extend(other.messages);
+        self.notes.extend(other.notes);
+    }
+
+    /// The collected messages, oldest first.
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    /// Render the collected warnings under a `warnings:` heading, or nothing when empty.
+    pub fn render(&self, painter: Painter) -> String {
+        if self.messages.is_empty() {
+            return String::new();
+        }
+
+        let mut output = format!("{}\n", painter.yellow("warnings:"));
+        for message in &self.messages {
+            output.push_str(&format!("  - {message}\n"));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorChoice;
+
+    #[test]
+    fn renders_grouped_summary_only_when_non_empty() {
+        let painter = Painter::stderr(ColorChoice::Never);
+        let mut warnings = Warnings::default();
+        assert_eq!(warnings.render(painter), "");
+
+        warnings.push("skipped a.bin");
+        warnings.push("b.txt: invalid UTF-8");
+        assert_eq!(
+            warnings.render(painter),
+            "warnings:\n  - skipped a.bin\n  - b.txt: invalid UTF-8\n"
+        );
+    }
+
+    #[test]
+    fn notes_are_taken_separately_from_warnings() {
+        let mut warnings = Warnings::default();
+        warnings.note("preset 'src' base .");
+        warnings.push("skipped a.bin");
+        assert_eq!(warnings.take_notes(), ["preset 'src' base ."]);
+        assert!(warnings.take_notes().is_empty());
+        assert_eq!(warnings.messages(), ["skipped a.bin"]);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::process::Command;
+use tempfile::tempdir;
+
+/// Run the built `gf` binary in `dir` with no global config.
+fn gf(dir: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_gf"))
+        .args(args)
+        .arg("--no-global")
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn presets_resolve_against_the_config_directory_from_a_subdirectory() {
+    let dir = tempdir().unwrap();
+    let proj = dir.path().join("proj");
+    fs::create_dir_all(proj.join("src")).unwrap();
+    fs::create_dir_all(proj.join("sub")).unwrap();
+    fs::write(
+        proj.join(".gather-files.yaml"),
+        "version: 1\npresets:\n  code:\n    include: [\"src/**\"]\n",
+    )
+    .unwrap();
+    fs::write(proj.join("src/a.rs"), "fn a() {}\n").unwrap();
+
+    let output = gf(&proj.join("sub"), &["code", "--stdout"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "-------\n# src/a.rs\n\nfn a() {}\n\n");
+}
+
+#[test]
+fn summary_counts_rendered_files_not_skipped_binaries() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("a.txt"), "a\n").unwrap();
+    fs::write(root.join("logo.png"), b"\x89PNG\0\x01").unwrap();
+
+    let output = gf(root, &[".", "--stdout", "--color", "never"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains(" from 1 files ("), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[gf: skipped logo.png — binary file]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn stats_reuse_the_rendered_sections_instead_of_reading_files_again() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let runs = root.join("runs.log");
+    fs::write(
+        root.join(".gather-files.yaml"),
+        format!(
+            "version: 2\ncontent_filters:\n  txt: \"echo run >> '{}'; cat\"\n",
+            runs.display()
+        ),
+    )
+    .unwrap();
+    fs::write(root.join("a.txt"), "a\n").unwrap();
+
+    let output = gf(root, &["a.txt", "--stdout", "--stats", "--stats-json", "-"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("100.0%  a.txt"), "{stderr}");
+    assert!(stderr.contains("\"files\": 1"), "{stderr}");
+    assert_eq!(fs::read_to_string(&runs).unwrap(), "run\n");
+}